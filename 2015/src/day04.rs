@@ -1,31 +1,152 @@
+use std::time::Instant;
+
+use itertools::Itertools;
 use rayon::prelude::*;
 
+use crate::params;
+
+const BATCH_SIZE: u64 = 1_000_000;
+
+/// Mine with five leading zeros
 pub(crate) fn part_1(input: &str) -> String {
-    (0_u64..)
-        .step_by(250_000)
-        .find_map(|bound| {
-            (bound..bound + 250_000).into_par_iter().find_first(|num| {
-                let phrase = format!("{}{num}", input.trim());
-                let hash = md5::compute(phrase);
-                hash[0] == 0 && hash[1] == 0 && hash[2] & 0xf0 == 0
-            })
-        })
-        .unwrap()
-        .to_string()
+    Miner::from_params(input.trim(), 5).mine().to_string()
 }
 
+/// Mine with six leading zeros, or `--set zeros=N`
 pub(crate) fn part_2(input: &str) -> String {
-    (0_u64..)
-        .step_by(1_000_000)
-        .find_map(|bound| {
-            (bound..bound + 1_000_000)
-                .into_par_iter()
-                .find_first(|num| {
-                    let phrase = format!("{}{num}", input.trim());
-                    let hash = md5::compute(phrase);
-                    hash[0] == 0 && hash[1] == 0 && hash[2] == 0
-                })
+    let zeros = params::get("zeros").unwrap_or(6);
+    Miner::from_params(input.trim(), zeros).mine().to_string()
+}
+
+/// Parallel search for the lowest number that gives a hash with `zeros` leading zero nibbles.
+///
+/// Supported parameters are `start` (first number to check), `threads` (0 uses all cores) and
+/// `checkpoint` (file to save the position to after every batch, and resume from).
+#[derive(Debug, Clone)]
+struct Miner<'a> {
+    secret: &'a str,
+    zeros: usize,
+    start: Option<u64>,
+    threads: usize,
+    checkpoint: Option<String>,
+}
+
+impl<'a> Miner<'a> {
+    fn from_params(secret: &'a str, zeros: usize) -> Self {
+        Self {
+            secret,
+            zeros,
+            start: params::get("start"),
+            threads: params::get("threads").unwrap_or(0),
+            checkpoint: params::get("checkpoint"),
+        }
+    }
+
+    fn mine(&self) -> u64 {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()
+            .unwrap();
+        let mut prefix = md5::Context::new();
+        prefix.consume(self.secret);
+
+        let start = self.start.unwrap_or(0);
+        let mut bound = start;
+        if let Some(checkpoint) = self.resume() {
+            // the lowest number from an earlier start is also the lowest from a later one
+            if let Some(found) = checkpoint.found.filter(|&found| found >= start) {
+                log::info!("Found {found} before");
+                return found;
+            }
+            match self.start {
+                Some(start) if start >= checkpoint.position => {}
+                Some(start) => {
+                    log::warn!(
+                        "Checked up to {} before, skipping ahead from start {start}",
+                        checkpoint.position
+                    );
+                    bound = checkpoint.position;
+                }
+                None => {
+                    log::info!("Resuming from {}", checkpoint.position);
+                    bound = checkpoint.position;
+                }
+            }
+        }
+        let started = Instant::now();
+        let mut hashes = 0;
+        loop {
+            let found = pool.install(|| {
+                (bound..bound + BATCH_SIZE)
+                    .into_par_iter()
+                    .find_first(|num| {
+                        let mut context = prefix.clone();
+                        context.consume(num.to_string());
+                        leading_zeros(&context.compute()) >= self.zeros
+                    })
+            });
+
+            hashes += BATCH_SIZE;
+            log::debug!(
+                "Checked up to {}, {:.0} hashes/s",
+                bound + BATCH_SIZE,
+                hashes as f64 / started.elapsed().as_secs_f64()
+            );
+
+            bound += BATCH_SIZE;
+            self.save(bound, found);
+            if let Some(found) = found {
+                return found;
+            }
+        }
+    }
+
+    /// Checkpoint saved in the checkpoint file for the same secret and difficulty
+    fn resume(&self) -> Option<Checkpoint> {
+        let content = std::fs::read_to_string(self.checkpoint.as_ref()?).ok()?;
+        content.lines().find_map(|l| {
+            let mut fields = l.split_whitespace();
+            if fields.next()? != self.secret || fields.next()?.parse() != Ok(self.zeros) {
+                return None;
+            }
+            Some(Checkpoint {
+                position: fields.next()?.parse().ok()?,
+                found: fields.next().and_then(|found| found.parse().ok()),
+            })
         })
-        .unwrap()
-        .to_string()
+    }
+
+    /// Save the position checked up to and the number found if there is one, keeping the
+    /// checkpoints of other secrets and difficulties
+    fn save(&self, position: u64, found: Option<u64>) {
+        let Some(checkpoint) = &self.checkpoint else {
+            return;
+        };
+        let key = format!("{} {} ", self.secret, self.zeros);
+        let line = match found {
+            Some(found) => format!("{key}{position} {found}"),
+            None => format!("{key}{position}"),
+        };
+        let content = std::fs::read_to_string(checkpoint).unwrap_or_default();
+        let content = content
+            .lines()
+            .filter(|l| !l.starts_with(&key))
+            .chain(std::iter::once(line.as_str()))
+            .join("\n");
+        std::fs::write(checkpoint, content + "\n")
+            .expect("Should have been able to save the position");
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Checkpoint {
+    /// The search got up to here, from wherever it started
+    position: u64,
+    found: Option<u64>,
+}
+
+fn leading_zeros(hash: &md5::Digest) -> usize {
+    hash.iter()
+        .position(|&b| b != 0)
+        .map_or(32, |idx| idx * 2 + usize::from(hash[idx] & 0xf0 == 0))
 }
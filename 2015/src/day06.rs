@@ -1,57 +1,62 @@
-use itertools::Itertools;
+use std::io::Write;
+
 use parse_display::FromStr;
 
+use crate::params;
+
+const DEFAULT_SIZE: usize = 1000;
+const MAX_IMAGE_SIZE: usize = 1000;
+
+/// Supports `--set size=N` for an N×N grid and `--set image=path` to save the lights as PGM
 pub(crate) fn part_1(input: &str) -> String {
-    let mut lights = vec![vec![false; 1000]; 1000];
-    input
-        .lines()
-        .map(|l| l.parse().unwrap())
-        .for_each(|action| {
-            match action {
-                Action::TurnOn(_, _, _, _) => action.range().for_each(|(row, col)| {
-                    lights[row][col] = true;
-                }),
-                Action::Toggle(_, _, _, _) => action.range().for_each(|(row, col)| {
-                    lights[row][col] = !lights[row][col];
-                }),
-                Action::TurnOff(_, _, _, _) => action.range().for_each(|(row, col)| {
-                    lights[row][col] = false;
-                }),
-            };
-        });
-
-    lights
-        .iter()
-        .flat_map(|row| row.iter())
-        .filter(|l| **l)
-        .count()
-        .to_string()
+    let grid = run(input, false, |action, on| match action {
+        Action::TurnOn(..) => true,
+        Action::Toggle(..) => !on,
+        Action::TurnOff(..) => false,
+    });
+    render_if_requested(&grid, "part_1", |&on| if on { u8::MAX } else { 0 });
+
+    grid.sum(|&on| u64::from(on)).to_string()
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    let mut lights = vec![vec![0_u32; 1000]; 1000];
-    input
+    let grid = run(input, 0_u32, |action, brightness| match action {
+        Action::TurnOn(..) => brightness + 1,
+        Action::Toggle(..) => brightness + 2,
+        Action::TurnOff(..) => brightness.saturating_sub(1),
+    });
+    let max = grid.cells.iter().copied().max().unwrap_or(0).max(1);
+    render_if_requested(&grid, "part_2", |&b| {
+        (u64::from(b) * u64::from(u8::MAX) / u64::from(max)) as u8
+    });
+
+    grid.sum(|&brightness| u64::from(brightness)).to_string()
+}
+
+fn run<T: Copy>(input: &str, initial: T, apply: fn(&Action, T) -> T) -> CompressedGrid<T> {
+    let size = params::get("size").unwrap_or(DEFAULT_SIZE);
+    let actions = input
         .lines()
         .map(|l| l.parse().unwrap())
-        .for_each(|action| {
-            match action {
-                Action::TurnOn(_, _, _, _) => action.range().for_each(|(row, col)| {
-                    lights[row][col] += 1;
-                }),
-                Action::Toggle(_, _, _, _) => action.range().for_each(|(row, col)| {
-                    lights[row][col] += 2;
-                }),
-                Action::TurnOff(_, _, _, _) => action.range().for_each(|(row, col)| {
-                    lights[row][col] = lights[row][col].saturating_sub(1);
-                }),
-            };
-        });
-
-    lights
-        .iter()
-        .flat_map(|row| row.iter())
-        .sum::<u32>()
-        .to_string()
+        .collect::<Vec<Action>>();
+
+    let mut grid = CompressedGrid::new(size, &actions, initial);
+    log::debug!(
+        "Compressed {size}x{size} grid to {}x{} regions",
+        grid.rows.len() - 1,
+        grid.cols.len() - 1
+    );
+    for action in &actions {
+        grid.apply(action, apply);
+    }
+    grid
+}
+
+fn render_if_requested<T: Copy>(grid: &CompressedGrid<T>, suffix: &str, shade: impl Fn(&T) -> u8) {
+    if let Some(path) = params::get_path("image", suffix) {
+        grid.render_pgm(&path, shade);
+        log::info!("Saved lights to {path}");
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, FromStr)]
@@ -65,17 +70,98 @@ enum Action {
 }
 
 impl Action {
-    fn range(&self) -> impl Iterator<Item = (usize, usize)> {
+    /// Half-open row and column ranges
+    fn bounds(&self) -> ((usize, usize), (usize, usize)) {
         match self {
-            Action::TurnOn(row_from, row_to, col_from, col_to) => {
-                (*row_from..=*row_to).cartesian_product(*col_from..=*col_to)
+            Action::TurnOn(row_from, row_to, col_from, col_to)
+            | Action::Toggle(row_from, row_to, col_from, col_to)
+            | Action::TurnOff(row_from, row_to, col_from, col_to) => {
+                ((*row_from, row_to + 1), (*col_from, col_to + 1))
             }
-            Action::Toggle(row_from, row_to, col_from, col_to) => {
-                (*row_from..=*row_to).cartesian_product(*col_from..=*col_to)
+        }
+    }
+}
+
+/// Grid split along all rectangle edges, so every region is always uniformly lit
+#[derive(Debug, Clone)]
+struct CompressedGrid<T> {
+    rows: Vec<usize>,
+    cols: Vec<usize>,
+    cells: Vec<T>,
+}
+
+impl<T: Copy> CompressedGrid<T> {
+    fn new(size: usize, actions: &[Action], initial: T) -> Self {
+        let mut rows = vec![0, size];
+        let mut cols = vec![0, size];
+        for action in actions {
+            let ((row_from, row_to), (col_from, col_to)) = action.bounds();
+            assert!(
+                row_to <= size && col_to <= size,
+                "{action:?} is outside of the {size}x{size} grid"
+            );
+            rows.extend([row_from, row_to]);
+            cols.extend([col_from, col_to]);
+        }
+        rows.sort_unstable();
+        rows.dedup();
+        cols.sort_unstable();
+        cols.dedup();
+
+        let cells = vec![initial; (rows.len() - 1) * (cols.len() - 1)];
+        Self { rows, cols, cells }
+    }
+
+    fn apply(&mut self, action: &Action, apply: fn(&Action, T) -> T) {
+        let ((row_from, row_to), (col_from, col_to)) = action.bounds();
+        let width = self.cols.len() - 1;
+        let col_range = Self::region(&self.cols, col_from)..Self::region(&self.cols, col_to);
+        for row in Self::region(&self.rows, row_from)..Self::region(&self.rows, row_to) {
+            for cell in &mut self.cells[row * width + col_range.start..row * width + col_range.end]
+            {
+                *cell = apply(action, *cell);
             }
-            Action::TurnOff(row_from, row_to, col_from, col_to) => {
-                (*row_from..=*row_to).cartesian_product(*col_from..=*col_to)
+        }
+    }
+
+    /// Index of the region starting at the boundary `pos`
+    fn region(boundaries: &[usize], pos: usize) -> usize {
+        boundaries.binary_search(&pos).unwrap()
+    }
+
+    fn sum(&self, value: fn(&T) -> u64) -> u64 {
+        let width = self.cols.len() - 1;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| {
+                let (row, col) = (idx / width, idx % width);
+                let area =
+                    (self.rows[row + 1] - self.rows[row]) * (self.cols[col + 1] - self.cols[col]);
+                value(cell) * area as u64
+            })
+            .sum()
+    }
+
+    /// Value at an uncompressed position
+    fn get(&self, row: usize, col: usize) -> &T {
+        let region = |boundaries: &[usize], pos| boundaries.partition_point(|&b| b <= pos) - 1;
+        &self.cells[region(&self.rows, row) * (self.cols.len() - 1) + region(&self.cols, col)]
+    }
+
+    /// Save as binary PGM, large grids are scaled down by sampling
+    fn render_pgm(&self, path: &str, shade: impl Fn(&T) -> u8) {
+        let size = *self.rows.last().unwrap();
+        let pixels = size.min(MAX_IMAGE_SIZE);
+        let mut data = format!("P5\n{pixels} {pixels}\n255\n").into_bytes();
+        for y in 0..pixels {
+            for x in 0..pixels {
+                let pos = |p: usize| (2 * p + 1) * size / (2 * pixels);
+                data.push(shade(self.get(pos(x), pos(y))));
             }
         }
+        std::fs::File::create(path)
+            .and_then(|mut f| f.write_all(&data))
+            .expect("Should have been able to write the image");
     }
 }
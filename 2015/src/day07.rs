@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display, fmt::Write};
+
+use itertools::Itertools;
 
 use nom::{
     branch::alt,
@@ -9,42 +11,163 @@ use nom::{
     Finish, IResult,
 };
 
+use crate::params;
+
+/// Supports `--set wire.NAME=value` to override wires and `--set dot=path` to export the circuit
 pub(crate) fn part_1(input: &str) -> String {
-    let instructions = input
-        .lines()
-        .map(|l| {
-            let (ins, var) = l.split_once(" -> ").unwrap();
-            (var, ins.into())
-        })
-        .collect::<HashMap<&str, Instruction>>();
-
-    instructions
-        .get("a")
-        .unwrap()
-        .value(&instructions, &mut HashMap::new())
-        .to_string()
+    let mut circuit = Circuit::from(input);
+    circuit.override_from_params();
+    circuit.solve("part_1")["a"].to_string()
 }
 
+/// Override `b` with the part 1 signal of `a`
 pub(crate) fn part_2(input: &str) -> String {
-    let mut instructions = input
-        .lines()
-        .map(|l| {
-            let (ins, var) = l.split_once(" -> ").unwrap();
-            (var, ins.into())
-        })
-        .collect::<HashMap<&str, Instruction>>();
-
-    let a = instructions
-        .get("a")
-        .unwrap()
-        .value(&instructions, &mut HashMap::new());
-    instructions.insert("b", Instruction::Identity(Data::Constant(a)));
-
-    instructions
-        .get("a")
-        .unwrap()
-        .value(&instructions, &mut HashMap::new())
-        .to_string()
+    let mut circuit = Circuit::from(input);
+    circuit.override_from_params();
+    let a = circuit.evaluate().unwrap_or_else(|err| panic!("{err}"))["a"];
+    circuit.override_wire("b", a);
+    circuit.solve("part_2")["a"].to_string()
+}
+
+#[derive(Debug, Clone)]
+struct Circuit<'a> {
+    gates: HashMap<&'a str, Instruction<'a>>,
+}
+
+impl<'a> Circuit<'a> {
+    /// Drive `wire` with a constant signal instead of its gate
+    fn override_wire(&mut self, wire: &'a str, value: u16) {
+        self.gates
+            .insert(wire, Instruction::Identity(Data::Constant(value)));
+    }
+
+    /// Parameters `wire.NAME` override a wire
+    fn override_from_params(&mut self) {
+        for (name, value) in params::prefixed("wire.") {
+            let Some((&wire, _)) = self.gates.get_key_value(name) else {
+                panic!("There is no wire {name} to override");
+            };
+            let value = value
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid signal {value:?} for wire {wire}"));
+            log::debug!("Overriding wire {wire} with {value}");
+            self.override_wire(wire, value);
+        }
+    }
+
+    /// Evaluate, and export to the file given by `--set dot=path` if requested
+    fn solve(&self, suffix: &str) -> HashMap<&'a str, u16> {
+        let res = self.evaluate();
+        if let Some(path) = params::get_path("dot", suffix) {
+            let values = res.as_ref().map_or_else(|_| HashMap::new(), Clone::clone);
+            std::fs::write(&path, self.to_dot(&values))
+                .expect("Should have been able to write the circuit");
+            log::info!("Saved circuit to {path}");
+        }
+        let values = res.unwrap_or_else(|err| panic!("{err}"));
+        log::debug!("Evaluated {} wires", values.len());
+        values
+    }
+
+    /// Signals of all wires
+    fn evaluate(&self) -> Result<HashMap<&'a str, u16>, Cycle<'a>> {
+        let mut values = HashMap::new();
+        let mut path = Vec::new();
+        for wire in self.gates.keys() {
+            self.wire_value(wire, &mut values, &mut path)?;
+        }
+        Ok(values)
+    }
+
+    /// Depth-first evaluation, `path` holds the wires currently being evaluated
+    fn wire_value(
+        &self,
+        wire: &'a str,
+        values: &mut HashMap<&'a str, u16>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<u16, Cycle<'a>> {
+        if let Some(&value) = values.get(wire) {
+            return Ok(value);
+        }
+        if let Some(idx) = path.iter().position(|&w| w == wire) {
+            let mut wires = path[idx..].to_vec();
+            wires.push(wire);
+            return Err(Cycle(wires));
+        }
+        let gate = self
+            .gates
+            .get(wire)
+            .unwrap_or_else(|| panic!("Wire {wire} has no input"));
+
+        path.push(wire);
+        let mut inputs = gate.inputs().map(|data| match data {
+            Data::Variable(input) => self.wire_value(input, values, path),
+            Data::Constant(value) => Ok(value),
+        });
+        let a = inputs.next().unwrap()?;
+        let b = inputs.next().transpose()?;
+        path.pop();
+
+        let value = gate.apply(a, b.unwrap_or_default());
+        values.insert(wire, value);
+        Ok(value)
+    }
+
+    /// Graphviz DOT, edges are labelled with the signals in `values` when known
+    fn to_dot(&self, values: &HashMap<&str, u16>) -> String {
+        let label = |value: Option<u16>| value.map_or_else(String::new, |v| v.to_string());
+        let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n");
+        for (&wire, gate) in self.gates.iter().sorted_by_key(|(&wire, _)| wire) {
+            let output = label(values.get(wire).copied());
+            let target = match gate {
+                Instruction::Identity(_) => wire.to_owned(),
+                _ => {
+                    let node = format!("{wire}_gate");
+                    writeln!(dot, "    {node} [label=\"{}\", shape=box];", gate.name()).unwrap();
+                    writeln!(dot, "    {node} -> {wire} [label=\"{output}\"];").unwrap();
+                    node
+                }
+            };
+            for (idx, data) in gate.inputs().enumerate() {
+                match data {
+                    Data::Variable(input) => {
+                        let value = label(values.get(input).copied());
+                        writeln!(dot, "    {input} -> {target} [label=\"{value}\"];").unwrap();
+                    }
+                    Data::Constant(value) => {
+                        let node = format!("{wire}_const_{idx}");
+                        writeln!(dot, "    {node} [label=\"{value}\", shape=plaintext];").unwrap();
+                        writeln!(dot, "    {node} -> {target} [label=\"{value}\"];").unwrap();
+                    }
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<'a> From<&'a str> for Circuit<'a> {
+    fn from(value: &'a str) -> Self {
+        let gates = value
+            .lines()
+            .map(|l| {
+                let (ins, wire) = l.split_once(" -> ").unwrap();
+                (wire, ins.into())
+            })
+            .collect();
+        Self { gates }
+    }
+}
+
+/// Wires that depend on themselves, the first wire is repeated at the end
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cycle<'a>(Vec<&'a str>);
+
+impl Display for Cycle<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Circuit has a cycle: {}", self.0.join(" -> "))
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -58,31 +181,44 @@ enum Instruction<'a> {
 }
 
 impl<'a> Instruction<'a> {
-    fn value(
-        &self,
-        instructions: &'a HashMap<&'a str, Instruction>,
-        cache: &mut HashMap<&'a str, u16>,
-    ) -> u16 {
+    fn inputs(&self) -> impl Iterator<Item = Data<'a>> {
+        let (a, b) = match *self {
+            Instruction::Identity(a) | Instruction::BitNot(a) => (a, None),
+            Instruction::BitAnd(a, b)
+            | Instruction::BitOr(a, b)
+            | Instruction::LShift(a, b)
+            | Instruction::RShift(a, b) => (a, Some(b)),
+        };
+        std::iter::once(a).chain(b)
+    }
+
+    /// Output for the input signals, `b` is ignored by unary gates
+    fn apply(&self, a: u16, b: u16) -> u16 {
         match self {
-            Instruction::Identity(d) => d.value(instructions, cache),
-            Instruction::BitAnd(a, b) => {
-                a.value(instructions, cache) & b.value(instructions, cache)
-            }
-            Instruction::BitOr(a, b) => a.value(instructions, cache) | b.value(instructions, cache),
-            Instruction::BitNot(d) => !d.value(instructions, cache),
-            Instruction::LShift(a, b) => {
-                a.value(instructions, cache) << b.value(instructions, cache)
-            }
-            Instruction::RShift(a, b) => {
-                a.value(instructions, cache) >> b.value(instructions, cache)
-            }
+            Instruction::Identity(_) => a,
+            Instruction::BitAnd(..) => a & b,
+            Instruction::BitOr(..) => a | b,
+            Instruction::BitNot(_) => !a,
+            Instruction::LShift(..) => a << b,
+            Instruction::RShift(..) => a >> b,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Instruction::Identity(_) => "",
+            Instruction::BitAnd(..) => "AND",
+            Instruction::BitOr(..) => "OR",
+            Instruction::BitNot(_) => "NOT",
+            Instruction::LShift(..) => "LSHIFT",
+            Instruction::RShift(..) => "RSHIFT",
         }
     }
 }
 
 impl<'a> From<&'a str> for Instruction<'a> {
     fn from(value: &'a str) -> Self {
-        fn parse(input: &str) -> IResult<&str, Instruction> {
+        fn parse(input: &str) -> IResult<&str, Instruction<'_>> {
             alt((
                 map(
                     tuple((
@@ -130,30 +266,9 @@ enum Data<'a> {
     Constant(u16),
 }
 
-impl<'a> Data<'a> {
-    fn value(
-        &self,
-        instructions: &'a HashMap<&'a str, Instruction>,
-        cache: &mut HashMap<&'a str, u16>,
-    ) -> u16 {
-        match self {
-            Data::Variable(name) => {
-                if let Some(val) = cache.get(name) {
-                    *val
-                } else {
-                    let val = instructions.get(name).unwrap().value(instructions, cache);
-                    cache.insert(*name, val);
-                    val
-                }
-            }
-            Data::Constant(val) => *val,
-        }
-    }
-}
-
 impl<'a> From<&'a str> for Data<'a> {
     fn from(value: &'a str) -> Self {
-        fn parse(input: &str) -> IResult<&str, Data> {
+        fn parse(input: &str) -> IResult<&str, Data<'_>> {
             alt((map(u16, Data::Constant), map(alpha1, Data::Variable)))(input)
         }
 
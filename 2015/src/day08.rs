@@ -1,17 +1,11 @@
+use std::fmt::Display;
+
 pub(crate) fn part_1(input: &str) -> String {
     input
         .lines()
         .map(|l| {
-            let mut bytes = l.as_bytes().iter().skip(1);
-            let mut count = 0;
-            while let Some(c) = bytes.next() {
-                if c == &b'\\' && bytes.next() == Some(&b'x') {
-                    bytes.next();
-                    bytes.next();
-                }
-                count += 1;
-            }
-            l.as_bytes().len() - count + 1
+            let bytes = decode(l).unwrap_or_else(|err| panic!("{l}: {err}"));
+            l.len() - bytes.len()
         })
         .sum::<usize>()
         .to_string()
@@ -20,18 +14,137 @@ pub(crate) fn part_1(input: &str) -> String {
 pub(crate) fn part_2(input: &str) -> String {
     input
         .lines()
-        .map(|l| {
-            l.as_bytes()
-                .iter()
-                .map(|c| match c {
-                    b'\\' => 2,
-                    b'"' => 2,
-                    _ => 1,
-                })
-                .sum::<usize>()
-                + 2
-                - l.as_bytes().len()
-        })
+        .map(|l| encode(l.as_bytes()).len() - l.len())
         .sum::<usize>()
         .to_string()
 }
+
+/// Bytes of a quoted literal with `\\`, `\"` and `\xNN` escapes
+fn decode(literal: &str) -> Result<Vec<u8>, DecodeError> {
+    let bytes = literal.as_bytes();
+    let error = |position, kind| Err(DecodeError { position, kind });
+    if bytes.first() != Some(&b'"') {
+        return error(0, ErrorKind::MissingQuote);
+    }
+
+    let mut res = Vec::new();
+    let mut pos = 1;
+    loop {
+        match bytes.get(pos) {
+            None => return error(pos, ErrorKind::MissingQuote),
+            Some(b'"') if pos + 1 == bytes.len() => return Ok(res),
+            Some(b'"') => return error(pos, ErrorKind::UnescapedQuote),
+            Some(b'\\') => match bytes.get(pos + 1) {
+                Some(&c @ (b'\\' | b'"')) if pos + 2 < bytes.len() => {
+                    res.push(c);
+                    pos += 2;
+                }
+                Some(b'x') => {
+                    let digits = bytes.get(pos + 2..pos + 4);
+                    match digits.filter(|d| d.iter().all(u8::is_ascii_hexdigit)) {
+                        Some(digits) => {
+                            let digits = std::str::from_utf8(digits).unwrap();
+                            res.push(u8::from_str_radix(digits, 16).unwrap());
+                            pos += 4;
+                        }
+                        None => return error(pos, ErrorKind::TruncatedHex),
+                    }
+                }
+                Some(b'\\' | b'"') | None => return error(pos, ErrorKind::TrailingBackslash),
+                Some(&c) => return error(pos, ErrorKind::UnknownEscape(c as char)),
+            },
+            Some(&c) => {
+                res.push(c);
+                pos += 1;
+            }
+        }
+    }
+}
+
+/// Quoted literal, bytes outside of printable ASCII are written as `\xNN`
+fn encode(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len() + 2);
+    res.push('"');
+    for &b in bytes {
+        match b {
+            b'\\' => res.push_str("\\\\"),
+            b'"' => res.push_str("\\\""),
+            b' '..=b'~' => res.push(b as char),
+            _ => res.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct DecodeError {
+    /// Byte offset of the offending character or escape
+    position: usize,
+    kind: ErrorKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ErrorKind {
+    MissingQuote,
+    UnescapedQuote,
+    TrailingBackslash,
+    TruncatedHex,
+    UnknownEscape(char),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ErrorKind::MissingQuote => write!(f, "missing quote at {}", self.position),
+            ErrorKind::UnescapedQuote => write!(f, "unescaped quote at {}", self.position),
+            ErrorKind::TrailingBackslash => write!(f, "trailing backslash at {}", self.position),
+            ErrorKind::TruncatedHex => write!(f, "truncated \\x escape at {}", self.position),
+            ErrorKind::UnknownEscape(c) => {
+                write!(f, "unknown escape \\{c} at {}", self.position)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn decode_inverts_encode(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            prop_assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+        }
+
+        #[test]
+        fn decode_inverts_encode_of_escapes(
+            bytes in prop::collection::vec(
+                prop_oneof![0x00..=0x1f_u8, 0x7f..=0xff_u8, Just(b'\\'), Just(b'"'), Just(b'x')],
+                0..64,
+            )
+        ) {
+            prop_assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+        }
+    }
+
+    #[test]
+    fn encode_escapes() {
+        assert_eq!(encode(b"a\"\\\x00\x7f\xff"), r#""a\"\\\x00\x7f\xff""#);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let error = |position, kind| Err(DecodeError { position, kind });
+        assert_eq!(decode(r#""\x4""#), error(1, ErrorKind::TruncatedHex));
+        assert_eq!(decode(r#""\x""#), error(1, ErrorKind::TruncatedHex));
+        assert_eq!(decode(r#""abc\""#), error(4, ErrorKind::TrailingBackslash));
+        assert_eq!(decode(r#""abc\"#), error(4, ErrorKind::TrailingBackslash));
+        assert_eq!(decode(r#""a\qb""#), error(2, ErrorKind::UnknownEscape('q')));
+        assert_eq!(decode(r#""a"b""#), error(2, ErrorKind::UnescapedQuote));
+        assert_eq!(decode(r#""ab"#), error(3, ErrorKind::MissingQuote));
+        assert_eq!(decode("ab\""), error(0, ErrorKind::MissingQuote));
+    }
+}
@@ -7,36 +7,43 @@ use nom::{
     sequence::{delimited, tuple},
     Finish, IResult,
 };
-use rayon::prelude::*;
+
+use crate::route::{self, Objective};
 
 pub(crate) fn part_1(input: &str) -> String {
-    distances(parse_input(input)).0.to_string()
+    best_route(parse_input(input), Objective::Shortest).to_string()
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    distances(parse_input(input)).1.to_string()
+    best_route(parse_input(input), Objective::Longest).to_string()
 }
 
-fn distances((locations, distances): (Vec<&str>, HashMap<(&str, &str), u32>)) -> (u32, u32) {
-    locations
+fn best_route(
+    (locations, distances): (Vec<&str>, HashMap<(&str, &str), u32>),
+    objective: Objective,
+) -> i64 {
+    let matrix = locations
         .iter()
-        .copied()
-        .permutations(locations.len())
-        .collect_vec()
-        .par_iter()
-        .map(|route| {
-            route
+        .map(|&a| {
+            locations
                 .iter()
-                .copied()
-                .tuple_windows()
-                .map(|c| distances.get(&c).unwrap())
-                .sum::<u32>()
+                .map(|&b| {
+                    if a == b {
+                        0
+                    } else {
+                        i64::from(distances[&(a, b)])
+                    }
+                })
+                .collect()
         })
-        .map(|i| (i, i))
-        .reduce(
-            || (u32::MAX, u32::MIN),
-            |acc, curr| (acc.0.min(curr.0), acc.1.max(curr.1)),
-        )
+        .collect_vec();
+
+    let route = route::path(&matrix, objective);
+    log::debug!(
+        "Route: {}",
+        route.nodes.iter().map(|&i| locations[i]).join(" -> ")
+    );
+    route.length
 }
 
 fn parse_input(input: &str) -> (Vec<&str>, HashMap<(&str, &str), u32>) {
@@ -1,53 +1,347 @@
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+use crate::params;
+
+/// Every sequence splits into elements after at most 24 steps, so this is only a safeguard
+const MAX_DIRECT_STEPS: usize = 40;
+
+/// Supports `--set steps=N` instead of 40 steps
 pub(crate) fn part_1(input: &str) -> String {
-    look_and_say_steps(
-        input
-            .trim()
-            .as_bytes()
-            .iter()
-            .map(|c| c - b'0')
-            .collect::<Vec<_>>(),
-        40,
-    )
-    .len()
-    .to_string()
+    let steps = params::get("steps").unwrap_or(40);
+    Chemistry::new()
+        .length(&parse_digits(input), steps)
+        .to_string()
 }
 
+/// Supports `--set steps=N` instead of 50 steps
 pub(crate) fn part_2(input: &str) -> String {
-    look_and_say_steps(
-        input
-            .trim()
-            .as_bytes()
-            .iter()
-            .map(|c| c - b'0')
-            .collect::<Vec<_>>(),
-        50,
-    )
-    .len()
-    .to_string()
+    let steps = params::get("steps").unwrap_or(50);
+    Chemistry::new()
+        .length(&parse_digits(input), steps)
+        .to_string()
+}
+
+pub(crate) fn part_1_direct(input: &str) -> String {
+    let steps = params::get("steps").unwrap_or(40);
+    look_and_say_steps(parse_digits(input), steps)
+        .len()
+        .to_string()
+}
+
+pub(crate) fn part_2_direct(input: &str) -> String {
+    let steps = params::get("steps").unwrap_or(50);
+    look_and_say_steps(parse_digits(input), steps)
+        .len()
+        .to_string()
+}
+
+fn parse_digits(input: &str) -> Vec<u8> {
+    input.trim().as_bytes().iter().map(|c| c - b'0').collect()
 }
 
 fn look_and_say_steps(nums: Vec<u8>, steps: usize) -> Vec<u8> {
-    itertools::iterate(nums, look_and_say_step)
-        .skip(1)
-        .take(steps)
-        .last()
-        .unwrap()
+    (0..steps).fold(nums, |n, _| look_and_say_step(&n))
 }
 
-fn look_and_say_step(nums: &Vec<u8>) -> Vec<u8> {
+/// Runs of more than nine digits only appear in the seed, their count takes several digits
+fn look_and_say_step(nums: &[u8]) -> Vec<u8> {
     let mut new = Vec::with_capacity(nums.len() * 27 / 20);
-    let mut idx = 0;
-    while idx < nums.len() {
-        if idx + 2 < nums.len() && nums[idx] == nums[idx + 1] && nums[idx + 1] == nums[idx + 2] {
-            new.extend([3, nums[idx]]);
-            idx += 3;
-        } else if idx + 1 < nums.len() && nums[idx] == nums[idx + 1] {
-            new.extend([2, nums[idx]]);
-            idx += 2;
+    for (count, &digit) in nums.iter().dedup_with_count() {
+        if count < 10 {
+            new.push(count as u8);
         } else {
-            new.extend([1, nums[idx]]);
-            idx += 1;
+            new.extend(count.to_string().bytes().map(|c| c - b'0'));
         }
+        new.push(digit);
     }
     new
 }
+
+/// Conway's elements, which decay into each other without ever interacting. Next to the 92
+/// common ones there are two transuranic elements for every digit other than 1, 2 and 3.
+#[derive(Debug, Clone)]
+struct Chemistry {
+    names: Vec<String>,
+    digits: Vec<Vec<u8>>,
+    decays: Vec<Vec<usize>>,
+    /// Bitmask of the digits an element or any of its descendants starts with
+    leading: Vec<u16>,
+}
+
+impl Chemistry {
+    fn new() -> Self {
+        let transuranic = [0, 4, 5, 6, 7, 8, 9].into_iter().flat_map(|digit| {
+            TRANSURANIC_ELEMENTS.iter().map(move |(symbol, digits)| {
+                (format!("{symbol}{digit}"), format!("{digits}{digit}"))
+            })
+        });
+        let (names, digits): (Vec<_>, Vec<_>) = ELEMENTS
+            .iter()
+            .map(|(symbol, digits)| (symbol.to_string(), digits.to_string()))
+            .chain(transuranic)
+            .map(|(name, digits)| (name, digits.bytes().map(|c| c - b'0').collect_vec()))
+            .unzip();
+        let mut chemistry = Self {
+            names,
+            leading: vec![0; digits.len()],
+            decays: vec![],
+            digits,
+        };
+
+        let split = |chemistry: &Self| {
+            chemistry
+                .digits
+                .iter()
+                .map(|digits| chemistry.split(&look_and_say_step(digits)))
+                .collect::<Option<Vec<_>>>()
+                .expect("every element should decay into elements")
+        };
+        // the first split is unchecked, as the leading digits depend on the decays
+        chemistry.decays = split(&chemistry);
+        for (element, digits) in chemistry.digits.iter().enumerate() {
+            chemistry.leading[element] = 1 << digits[0];
+        }
+        loop {
+            let leading = (0..chemistry.digits.len())
+                .map(|e| chemistry.leading[e] | chemistry.leading[chemistry.decays[e][0]])
+                .collect_vec();
+            if leading == chemistry.leading {
+                break;
+            }
+            chemistry.leading = leading;
+        }
+        chemistry.decays = split(&chemistry);
+        chemistry
+    }
+
+    /// Decomposition into elements whose boundaries never merge. The digit left of a
+    /// boundary never changes, so it must differ from every digit the right side starts with.
+    fn split(&self, sequence: &[u8]) -> Option<Vec<usize>> {
+        // prev[end]: start and element of an atom that ends at end
+        let mut prev = vec![None; sequence.len() + 1];
+        for start in 0..sequence.len() {
+            if start > 0 && prev[start].is_none() {
+                continue;
+            }
+            for (element, digits) in self.digits.iter().enumerate() {
+                let end = start + digits.len();
+                if end <= sequence.len()
+                    && prev[end].is_none()
+                    && sequence[start..end] == digits[..]
+                    && (start == 0 || self.leading[element] & 1 << sequence[start - 1] == 0)
+                {
+                    prev[end] = Some((start, element));
+                }
+            }
+        }
+
+        let mut atoms = vec![];
+        let mut end = sequence.len();
+        while end > 0 {
+            let (start, element) = prev[end]?;
+            atoms.push(element);
+            end = start;
+        }
+        atoms.reverse();
+        Some(atoms)
+    }
+
+    /// Simulate directly until the sequence splits into elements, then count the elements
+    fn length(&self, digits: &[u8], steps: usize) -> BigUint {
+        let mut sequence = digits.to_vec();
+        let mut step = 0;
+        let atoms = loop {
+            if step == steps {
+                return BigUint::from(sequence.len());
+            }
+            if let Some(atoms) = self.split(&sequence) {
+                break atoms;
+            }
+            if step == MAX_DIRECT_STEPS {
+                log::warn!("Sequence doesn't split into elements, simulating all steps directly");
+                return BigUint::from(look_and_say_steps(sequence, steps - step).len());
+            }
+            sequence = look_and_say_step(&sequence);
+            step += 1;
+        };
+        log::trace!(
+            "Split into {} after {step} steps",
+            atoms.iter().map(|&e| &self.names[e]).join(".")
+        );
+
+        let mut counts = vec![BigUint::default(); self.digits.len()];
+        for atom in atoms {
+            counts[atom] += 1_u32;
+        }
+        for _ in step..steps {
+            let mut next = vec![BigUint::default(); self.digits.len()];
+            for (element, count) in counts.iter().enumerate() {
+                for &product in &self.decays[element] {
+                    next[product] += count;
+                }
+            }
+            counts = next;
+        }
+        counts
+            .iter()
+            .zip(&self.digits)
+            .map(|(count, digits)| count * digits.len())
+            .sum()
+    }
+}
+
+/// Symbols and digits of the transuranic elements without their last digit, which can be any
+/// digit other than 1, 2 and 3 and never changes
+const TRANSURANIC_ELEMENTS: [(&str, &str); 2] = [
+    ("Np", "1311222113321132211221121332211"),
+    ("Pu", "31221132221222112112322211"),
+];
+
+/// Symbols and digits of the common elements, in the order of atomic numbers
+const ELEMENTS: [(&str, &str); 92] = [
+    ("H", "22"),
+    ("He", "13112221133211322112211213322112"),
+    ("Li", "312211322212221121123222112"),
+    ("Be", "111312211312113221133211322112211213322112"),
+    ("B", "1321132122211322212221121123222112"),
+    ("C", "3113112211322112211213322112"),
+    ("N", "111312212221121123222112"),
+    ("O", "132112211213322112"),
+    ("F", "31121123222112"),
+    ("Ne", "111213322112"),
+    ("Na", "123222112"),
+    ("Mg", "3113322112"),
+    ("Al", "1113222112"),
+    ("Si", "1322112"),
+    ("P", "311311222112"),
+    ("S", "1113122112"),
+    ("Cl", "132112"),
+    ("Ar", "3112"),
+    ("K", "1112"),
+    ("Ca", "12"),
+    ("Sc", "3113112221133112"),
+    ("Ti", "11131221131112"),
+    ("V", "13211312"),
+    ("Cr", "31132"),
+    ("Mn", "111311222112"),
+    ("Fe", "13122112"),
+    ("Co", "32112"),
+    ("Ni", "11133112"),
+    ("Cu", "131112"),
+    ("Zn", "312"),
+    ("Ga", "13221133122211332"),
+    ("Ge", "31131122211311122113222"),
+    ("As", "11131221131211322113322112"),
+    ("Se", "13211321222113222112"),
+    ("Br", "3113112211322112"),
+    ("Kr", "11131221222112"),
+    ("Rb", "1321122112"),
+    ("Sr", "3112112"),
+    ("Y", "1112133"),
+    ("Zr", "12322211331222113112211"),
+    ("Nb", "1113122113322113111221131221"),
+    ("Mo", "13211322211312113211"),
+    ("Tc", "311322113212221"),
+    ("Ru", "132211331222113112211"),
+    ("Rh", "311311222113111221131221"),
+    ("Pd", "111312211312113211"),
+    ("Ag", "132113212221"),
+    ("Cd", "3113112211"),
+    ("In", "11131221"),
+    ("Sn", "13211"),
+    ("Sb", "3112221"),
+    ("Te", "1322113312211"),
+    ("I", "311311222113111221"),
+    ("Xe", "11131221131211"),
+    ("Cs", "13211321"),
+    ("Ba", "311311"),
+    ("La", "11131"),
+    ("Ce", "1321133112"),
+    ("Pr", "31131112"),
+    ("Nd", "111312"),
+    ("Pm", "132"),
+    ("Sm", "311332"),
+    ("Eu", "1113222"),
+    ("Gd", "13221133112"),
+    ("Tb", "3113112221131112"),
+    ("Dy", "111312211312"),
+    ("Ho", "1321132"),
+    ("Er", "311311222"),
+    ("Tm", "11131221133112"),
+    ("Yb", "1321131112"),
+    ("Lu", "311312"),
+    ("Hf", "11132"),
+    ("Ta", "13112221133211322112211213322113"),
+    ("W", "312211322212221121123222113"),
+    ("Re", "111312211312113221133211322112211213322113"),
+    ("Os", "1321132122211322212221121123222113"),
+    ("Ir", "3113112211322112211213322113"),
+    ("Pt", "111312212221121123222113"),
+    ("Au", "132112211213322113"),
+    ("Hg", "31121123222113"),
+    ("Tl", "111213322113"),
+    ("Pb", "123222113"),
+    ("Bi", "3113322113"),
+    ("Po", "1113222113"),
+    ("At", "1322113"),
+    ("Rn", "311311222113"),
+    ("Fr", "1113122113"),
+    ("Ra", "132113"),
+    ("Ac", "3113"),
+    ("Th", "1113"),
+    ("Pa", "13"),
+    ("U", "3"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The element counts must match the direct simulation, from before the sequence splits
+    /// into elements to well after
+    #[test]
+    fn elements_match_direct_simulation() {
+        let chemistry = Chemistry::new();
+        for seed in [
+            "1",
+            "3",
+            "22",
+            "1113222113",
+            "12345",
+            "4",
+            "0",
+            "9999",
+            "1234567890",
+            "3333333",
+            "111111111111",
+        ] {
+            let digits = parse_digits(seed);
+            let mut sequence = digits.clone();
+            for step in 0..=30 {
+                assert_eq!(
+                    chemistry.length(&digits, step),
+                    BigUint::from(sequence.len()),
+                    "seed {seed} after {step} steps"
+                );
+                sequence = look_and_say_step(&sequence);
+            }
+        }
+    }
+
+    #[test]
+    fn long_runs() {
+        assert_eq!(look_and_say_step(&parse_digits("1111")), [4, 1]);
+        assert_eq!(look_and_say_step(&parse_digits("3333333")), [7, 3]);
+        assert_eq!(look_and_say_step(&parse_digits("222222222222")), [1, 2, 2]);
+    }
+
+    #[test]
+    fn every_seed_splits() {
+        let chemistry = Chemistry::new();
+        for seed in ["12345", "4", "0", "9999", "1234567890", "3333333"] {
+            let sequence = look_and_say_steps(parse_digits(seed), 24);
+            assert!(chemistry.split(&sequence).is_some(), "seed {seed}");
+        }
+    }
+}
@@ -1,51 +1,160 @@
+use itertools::Itertools;
+
+use crate::params;
+
+/// Supports `--set list=N` to log the next N valid passwords
 pub(crate) fn part_1(input: &str) -> String {
-    let password: [u8; 8] = input.trim().as_bytes().try_into().unwrap();
-    let next = itertools::iterate(password, |p| inc_password(*p))
-        .find(valid_password)
-        .unwrap();
-    String::from_utf8(next.to_vec()).unwrap()
+    let policy = Policy::from_params();
+    if let Some(count) = params::get("list") {
+        for (idx, password) in policy.passwords(input.trim()).take(count).enumerate() {
+            log::info!("{:>4}: {password}", idx + 1);
+        }
+    }
+    let next = policy.passwords(input.trim()).next();
+    next.expect("no valid password left")
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    let password: [u8; 8] = input.trim().as_bytes().try_into().unwrap();
-    let next = itertools::iterate(password, |p| inc_password(*p))
-        .filter(valid_password)
+    Policy::from_params()
+        .passwords(input.trim())
         .nth(1)
-        .unwrap();
-    String::from_utf8(next.to_vec()).unwrap()
+        .expect("no valid password left")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    /// None of these letters, passwords that contain them are skipped at once
+    Forbidden(Vec<u8>),
+    /// A run of this many letters that follow each other in the alphabet
+    Straight(usize),
+    /// This many non-overlapping pairs of different letters
+    Pairs(usize),
 }
 
-fn inc_password(mut password: [u8; 8]) -> [u8; 8] {
-    let mut idx = 7;
-    loop {
-        password[idx] += 1;
-        if password[idx] > b'z' {
-            password[idx] = b'a';
-            idx = idx.checked_sub(1).unwrap_or(7);
-        } else {
-            break;
+impl Rule {
+    /// `password` holds the indices of the letters in the alphabet
+    fn check(&self, alphabet: &[u8], password: &[usize]) -> bool {
+        match self {
+            Rule::Forbidden(letters) => password.iter().all(|&c| !letters.contains(&alphabet[c])),
+            Rule::Straight(len) => password
+                .windows(*len)
+                .any(|w| w.iter().tuple_windows().all(|(a, b)| a + 1 == *b)),
+            Rule::Pairs(count) => {
+                let mut letters = vec![];
+                let mut idx = 0;
+                while idx + 1 < password.len() {
+                    if password[idx] == password[idx + 1] {
+                        letters.push(password[idx]);
+                        idx += 2;
+                    } else {
+                        idx += 1;
+                    }
+                }
+                letters.iter().unique().count() >= *count
+            }
         }
     }
-    password
 }
 
-fn valid_password(password: &[u8; 8]) -> bool {
-    let increasing = password
-        .windows(3)
-        .any(|w| w[0] + 1 == w[1] && w[1] + 1 == w[2]);
-    let none_forbidden = password
-        .iter()
-        .all(|c| c != &b'i' && c != &b'o' && c != &b'l');
-    let non_overlapping_pairs = password
-        .windows(2)
-        .enumerate()
-        .any(|(first_idx, first_val)| {
-            first_val[0] == first_val[1]
-                && password
-                    .windows(2)
-                    .skip(first_idx + 2)
-                    .any(|second_val| first_val != second_val && second_val[0] == second_val[1])
-        });
-
-    increasing && none_forbidden && non_overlapping_pairs
+/// Rules that passwords over an alphabet have to follow
+#[derive(Debug, Clone)]
+struct Policy {
+    alphabet: Vec<u8>,
+    rules: Vec<Rule>,
+    /// Letters that aren't forbidden by any rule, by index in the alphabet
+    allowed: Vec<bool>,
+}
+
+impl Policy {
+    fn new(alphabet: &str) -> Self {
+        Self {
+            alphabet: alphabet.bytes().collect(),
+            rules: vec![],
+            allowed: vec![true; alphabet.len()],
+        }
+    }
+
+    fn with(mut self, rule: Rule) -> Self {
+        if let Rule::Forbidden(letters) = &rule {
+            for (allowed, c) in self.allowed.iter_mut().zip(&self.alphabet) {
+                *allowed &= !letters.contains(c);
+            }
+        }
+        self.rules.push(rule);
+        self
+    }
+
+    /// Santa's policy, the parameters `alphabet`, `forbidden`, `straight` and `pairs` change it
+    fn from_params() -> Self {
+        let alphabet = params::get::<String>("alphabet");
+        let alphabet = alphabet.as_deref().unwrap_or("abcdefghijklmnopqrstuvwxyz");
+        let forbidden = params::get::<String>("forbidden");
+        let straight = params::get("straight").unwrap_or(3);
+        assert!(
+            (1..=alphabet.len()).contains(&straight),
+            "the straight has to be between 1 and {} letters long, not {straight}",
+            alphabet.len()
+        );
+        Policy::new(alphabet)
+            .with(Rule::Forbidden(
+                forbidden.unwrap_or("iol".to_owned()).into_bytes(),
+            ))
+            .with(Rule::Straight(straight))
+            .with(Rule::Pairs(params::get("pairs").unwrap_or(2)))
+    }
+
+    /// Valid passwords after `current`, in order up to the last one of that length
+    fn passwords<'a>(&'a self, current: &str) -> impl Iterator<Item = String> + 'a {
+        let mut password = current
+            .bytes()
+            .map(|c| {
+                self.alphabet
+                    .iter()
+                    .position(|&a| a == c)
+                    .unwrap_or_else(|| panic!("{:?} is not in the alphabet", c as char))
+            })
+            .collect_vec();
+        assert!(
+            self.allowed.contains(&true),
+            "the policy forbids every letter"
+        );
+
+        let mut checked = 0_usize;
+        std::iter::from_fn(move || loop {
+            if !self.increment(&mut password) {
+                return None;
+            }
+            checked += 1;
+            if self
+                .rules
+                .iter()
+                .all(|r| r.check(&self.alphabet, &password))
+            {
+                log::debug!("Checked {checked} passwords");
+                return Some(password.iter().map(|&c| self.alphabet[c] as char).collect());
+            }
+        })
+    }
+
+    /// Next password without forbidden letters, false when it wrapped around after the last one
+    fn increment(&self, password: &mut [usize]) -> bool {
+        let first = self.allowed.iter().position(|&a| a).unwrap();
+        // a forbidden letter has to change first, everything after it restarts at the lowest letter
+        let end = password
+            .iter()
+            .position(|&c| !self.allowed[c])
+            .map_or(password.len(), |idx| idx + 1);
+        password[end..].fill(first);
+
+        for c in password[..end].iter_mut().rev() {
+            match (*c + 1..self.alphabet.len()).find(|&n| self.allowed[n]) {
+                Some(next) => {
+                    *c = next;
+                    return true;
+                }
+                None => *c = first,
+            }
+        }
+        false
+    }
 }
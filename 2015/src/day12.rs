@@ -1,29 +1,352 @@
-use regex::Regex;
-use serde_json::Value;
+use std::cmp::Ordering;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    character::complete::{char, i64, space1, u64},
+    combinator::{all_consuming, map, opt},
+    sequence::{delimited, preceded, separated_pair, tuple},
+    Finish, IResult,
+};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+use crate::params;
+
+const RED: &str = r#"exclude object where any value == "red""#;
 
 pub(crate) fn part_1(input: &str) -> String {
-    let re = Regex::new(r"-?\d+").unwrap();
-    re.find_iter(input)
-        .map(|n| n.as_str().parse::<i64>().unwrap())
-        .sum::<i64>()
-        .to_string()
+    sum(input, &[]).to_string()
 }
 
+/// Supports `--set filter=...` instead of excluding red objects, several filters are separated by `;`
 pub(crate) fn part_2(input: &str) -> String {
-    count(&serde_json::from_str(input).unwrap()).to_string()
-}
-
-fn count(json: &Value) -> i64 {
-    match json {
-        Value::Null | Value::Bool(_) | Value::String(_) => 0,
-        Value::Number(n) => n.as_i64().unwrap(),
-        Value::Array(a) => a.iter().map(count).sum::<i64>(),
-        Value::Object(o) => {
-            if o.values().all(|v| v != &Value::String("red".to_string())) {
-                o.values().map(count).sum::<i64>()
-            } else {
-                0
+    let filters = params::get::<String>("filter").unwrap_or(RED.to_owned());
+    let filters = filters.split(';').map(Filter::from).collect::<Vec<_>>();
+    sum(input, &filters).to_string()
+}
+
+/// Sum of all numbers outside of excluded containers, without building the whole document
+fn sum(input: &str, filters: &[Filter]) -> i64 {
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let summary = Summer { filters, depth: 0 }
+        .deserialize(&mut deserializer)
+        .unwrap();
+    deserializer.end().unwrap();
+    log::debug!("Excluded {} objects and arrays", summary.excluded);
+    summary.sum
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Object,
+    Array,
+    Any,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    /// `ordering` is None for values of different types, which are only unequal
+    fn holds(&self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Op::Ne, None) => true,
+            (_, None) => false,
+            (Op::Eq, Some(o)) => o.is_eq(),
+            (Op::Ne, Some(o)) => o.is_ne(),
+            (Op::Lt, Some(o)) => o.is_lt(),
+            (Op::Le, Some(o)) => o.is_le(),
+            (Op::Gt, Some(o)) => o.is_gt(),
+            (Op::Ge, Some(o)) => o.is_ge(),
+        }
+    }
+}
+
+/// Scalar JSON value
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+}
+
+impl Literal {
+    fn compare(&self, other: &Literal) -> Option<Ordering> {
+        match (self, other) {
+            (Literal::Null, Literal::Null) => Some(Ordering::Equal),
+            (Literal::Bool(a), Literal::Bool(b)) => Some(a.cmp(b)),
+            (Literal::Number(a), Literal::Number(b)) => Some(a.cmp(b)),
+            (Literal::String(a), Literal::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    /// One of the direct children compares to the literal
+    AnyValue(Op, Literal),
+    /// One of the keys of an object compares to the string
+    Key(Op, String),
+    /// Nesting level of the container, the document itself is at depth 0
+    Depth(Op, u64),
+}
+
+/// `exclude <object|array|any> [where <condition>]`, with conditions like
+/// `any value == "red"`, `key != "a"` or `depth > 2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Filter {
+    kind: Kind,
+    condition: Option<Condition>,
+}
+
+/// What is known about a container while it's being read
+#[derive(Debug, Copy, Clone)]
+enum Check<'a> {
+    Entered(u64),
+    Key(&'a str),
+    Value(&'a Literal),
+}
+
+impl Filter {
+    fn excludes(&self, kind: Kind, check: Check) -> bool {
+        if self.kind != Kind::Any && self.kind != kind {
+            return false;
+        }
+        match (&self.condition, check) {
+            (None, Check::Entered(_)) => true,
+            (Some(Condition::Depth(op, n)), Check::Entered(depth)) => op.holds(Some(depth.cmp(n))),
+            (Some(Condition::Key(op, s)), Check::Key(key)) => op.holds(Some(key.cmp(s))),
+            (Some(Condition::AnyValue(op, literal)), Check::Value(value)) => {
+                op.holds(value.compare(literal))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<&str> for Filter {
+    fn from(value: &str) -> Self {
+        fn op(input: &str) -> IResult<&str, Op> {
+            alt((
+                map(tag("=="), |_| Op::Eq),
+                map(tag("!="), |_| Op::Ne),
+                map(tag("<="), |_| Op::Le),
+                map(tag(">="), |_| Op::Ge),
+                map(tag("<"), |_| Op::Lt),
+                map(tag(">"), |_| Op::Gt),
+            ))(input)
+        }
+
+        fn string(input: &str) -> IResult<&str, String> {
+            map(
+                delimited(char('"'), take_till(|c| c == '"'), char('"')),
+                str::to_owned,
+            )(input)
+        }
+
+        fn literal(input: &str) -> IResult<&str, Literal> {
+            alt((
+                map(tag("null"), |_| Literal::Null),
+                map(tag("true"), |_| Literal::Bool(true)),
+                map(tag("false"), |_| Literal::Bool(false)),
+                map(i64, Literal::Number),
+                map(string, Literal::String),
+            ))(input)
+        }
+
+        fn condition(input: &str) -> IResult<&str, Condition> {
+            alt((
+                map(
+                    preceded(tag("any value "), separated_pair(op, space1, literal)),
+                    |(op, literal)| Condition::AnyValue(op, literal),
+                ),
+                map(
+                    preceded(tag("key "), separated_pair(op, space1, string)),
+                    |(op, key)| Condition::Key(op, key),
+                ),
+                map(
+                    preceded(tag("depth "), separated_pair(op, space1, u64)),
+                    |(op, depth)| Condition::Depth(op, depth),
+                ),
+            ))(input)
+        }
+
+        fn parse(input: &str) -> IResult<&str, Filter> {
+            map(
+                tuple((
+                    preceded(
+                        tag("exclude "),
+                        alt((
+                            map(tag("object"), |_| Kind::Object),
+                            map(tag("array"), |_| Kind::Array),
+                            map(tag("any"), |_| Kind::Any),
+                        )),
+                    ),
+                    opt(preceded(tag(" where "), condition)),
+                )),
+                |(kind, condition)| Filter { kind, condition },
+            )(input)
+        }
+
+        all_consuming(parse)(value.trim())
+            .finish()
+            .unwrap_or_else(|err| panic!("Invalid filter {value:?}: {err}"))
+            .1
+    }
+}
+
+/// Sums a value while it's deserialized, containers are skipped once a filter excludes them
+#[derive(Debug, Copy, Clone)]
+struct Summer<'a> {
+    filters: &'a [Filter],
+    depth: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Summary {
+    sum: i64,
+    /// Number of excluded containers, not counting the ones inside of them
+    excluded: usize,
+    /// The value itself, if it's a scalar
+    scalar: Option<Literal>,
+}
+
+impl Summary {
+    fn scalar(literal: Literal) -> Self {
+        let sum = match literal {
+            Literal::Number(n) => n,
+            _ => 0,
+        };
+        Self {
+            sum,
+            excluded: 0,
+            scalar: Some(literal),
+        }
+    }
+
+    fn add(&mut self, child: Summary) {
+        self.sum += child.sum;
+        self.excluded += child.excluded;
+    }
+
+    fn finish(self, excluded: bool) -> Self {
+        if excluded {
+            Self {
+                sum: 0,
+                excluded: 1,
+                scalar: None,
+            }
+        } else {
+            self
+        }
+    }
+}
+
+impl Summer<'_> {
+    fn excludes(&self, kind: Kind, check: Check) -> bool {
+        self.filters.iter().any(|f| f.excludes(kind, check))
+    }
+
+    fn child(&self) -> Self {
+        Self {
+            filters: self.filters,
+            depth: self.depth + 1,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Summer<'_> {
+    type Value = Summary;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Summary, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Summer<'_> {
+    type Value = Summary;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a JSON document with integer numbers")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Summary, E> {
+        Ok(Summary::scalar(Literal::Null))
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Summary, E> {
+        Ok(Summary::scalar(Literal::Bool(v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Summary, E> {
+        Ok(Summary::scalar(Literal::Number(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Summary, E> {
+        let v = i64::try_from(v).map_err(E::custom)?;
+        Ok(Summary::scalar(Literal::Number(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Summary, E> {
+        Err(E::custom(format!("{v} is not an integer")))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Summary, E> {
+        Ok(Summary::scalar(Literal::String(v.to_owned())))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Summary, A::Error> {
+        let mut summary = Summary::default();
+        let mut excluded = self.excludes(Kind::Array, Check::Entered(self.depth));
+        loop {
+            if excluded {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                break;
+            }
+            let Some(child) = seq.next_element_seed(self.child())? else {
+                break;
+            };
+            excluded = child
+                .scalar
+                .as_ref()
+                .is_some_and(|v| self.excludes(Kind::Array, Check::Value(v)));
+            summary.add(child);
+        }
+        Ok(summary.finish(excluded))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Summary, A::Error> {
+        let mut summary = Summary::default();
+        let mut excluded = self.excludes(Kind::Object, Check::Entered(self.depth));
+        loop {
+            if excluded {
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                break;
+            }
+            let Some(key) = map.next_key::<String>()? else {
+                break;
+            };
+            if self.excludes(Kind::Object, Check::Key(&key)) {
+                map.next_value::<IgnoredAny>()?;
+                excluded = true;
+                continue;
             }
+            let child = map.next_value_seed(self.child())?;
+            excluded = child
+                .scalar
+                .as_ref()
+                .is_some_and(|v| self.excludes(Kind::Object, Check::Value(v)));
+            summary.add(child);
         }
+        Ok(summary.finish(excluded))
     }
 }
@@ -8,7 +8,8 @@ use nom::{
     sequence::{terminated, tuple},
     Finish, IResult,
 };
-use rayon::prelude::*;
+
+use crate::route::{self, Objective};
 
 pub(crate) fn part_1(input: &str) -> String {
     find_max(input.lines().map(parse_line).collect()).to_string()
@@ -26,26 +27,28 @@ pub(crate) fn part_2(input: &str) -> String {
 
 fn find_max(happiness: HashMap<(&str, &str), i64>) -> i64 {
     let names = happiness.keys().map(|k| k.0).sorted().dedup().collect_vec();
-    names
+    let matrix = names
         .iter()
-        .copied()
-        .permutations(names.len())
-        .map(|mut permutation| {
-            permutation.extend_from_within(..1);
-            permutation
-        })
-        .collect_vec()
-        .par_iter()
-        .map(|seating| {
-            seating
+        .map(|&a| {
+            names
                 .iter()
-                .copied()
-                .tuple_windows()
-                .map(|(a, b)| happiness.get(&(a, b)).unwrap() + happiness.get(&(b, a)).unwrap())
-                .sum::<i64>()
+                .map(|&b| {
+                    if a == b {
+                        0
+                    } else {
+                        happiness[&(a, b)] + happiness[&(b, a)]
+                    }
+                })
+                .collect()
         })
-        .max()
-        .unwrap()
+        .collect_vec();
+
+    let seating = route::cycle(&matrix, Objective::Longest);
+    log::debug!(
+        "Seating: {}",
+        seating.nodes.iter().map(|&i| names[i]).join(", ")
+    );
+    seating.length
 }
 
 fn parse_line(input: &str) -> ((&str, &str), i64) {
@@ -1,44 +1,45 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::io::{BufWriter, Write};
 
 use parse_display::FromStr;
+use serde::Serialize;
 
+use crate::params;
+
+const DURATION: u32 = 2503;
+
+/// Supports `--set duration=N` instead of 2503 seconds
 pub(crate) fn part_1(input: &str) -> String {
-    input
-        .lines()
-        .map(|l| l.parse::<Reindeer>().unwrap().distance_after(2503))
+    let duration = params::get("duration").unwrap_or(DURATION);
+    parse_reindeer(input)
+        .iter()
+        .map(|r| r.distance_after(duration))
         .max()
         .unwrap()
         .to_string()
 }
 
+/// Supports `--set timeline=path.csv` or `path.json` to export the standings of every second
 pub(crate) fn part_2(input: &str) -> String {
-    let reindeer = input
-        .lines()
-        .map(|l| l.parse::<Reindeer>().unwrap())
-        .collect::<Vec<_>>();
-    let mut points = HashMap::<_, u32>::new();
-
-    for time in 1..=2503 {
-        for furthest in reindeer
-            .iter()
-            .map(|r| (r, r.distance_after(time)))
-            .fold((vec![], u32::MIN), |mut acc, curr| {
-                match curr.1.cmp(&acc.1) {
-                    Ordering::Greater => (vec![curr.0], curr.1),
-                    Ordering::Equal => {
-                        acc.0.push(curr.0);
-                        acc
-                    }
-                    Ordering::Less => acc,
-                }
-            })
-            .0
-        {
-            *points.entry(furthest).or_default() += 1;
-        }
+    let reindeer = parse_reindeer(input);
+    let export = params::get::<String>("timeline");
+    let duration = params::get("duration").unwrap_or(DURATION);
+    let timeline = Timeline::race(&reindeer, duration, export.is_some());
+    for change in &timeline.lead_changes {
+        log::debug!(
+            "{:>5}s: {} in the lead",
+            change.time,
+            change.leaders.join(", ")
+        );
     }
+    if let Some(path) = export {
+        timeline.export(&path);
+        log::info!("Saved timeline to {path}");
+    }
+    timeline.points.iter().max().unwrap().to_string()
+}
 
-    points.values().max().unwrap().to_string()
+fn parse_reindeer(input: &str) -> Vec<Reindeer> {
+    input.lines().map(|l| l.parse().unwrap()).collect()
 }
 
 #[derive(Debug, Clone, FromStr, PartialEq, Eq, Hash)]
@@ -52,21 +53,115 @@ struct Reindeer {
 
 impl Reindeer {
     fn distance_after(&self, time: u32) -> u32 {
-        let cycles = time / self.cycle_length();
-        let remaining = time % self.cycle_length();
-        let remaining = if remaining < self.flying_time {
-            self.speed * remaining
-        } else {
-            self.cycle_distance()
-        };
-        cycles * self.cycle_distance() + remaining
+        let cycle = self.flying_time + self.resting_time;
+        let flown = time / cycle * self.flying_time + (time % cycle).min(self.flying_time);
+        flown * self.speed
     }
 
-    fn cycle_distance(&self) -> u32 {
-        self.speed * self.flying_time
+    /// Whether the reindeer flies during the second that starts at `time`
+    fn flying(&self, time: u32) -> bool {
+        time % (self.flying_time + self.resting_time) < self.flying_time
+    }
+}
+
+/// Final standings of the race and when the lead changed, reindeer are in input order
+#[derive(Debug, Clone, Serialize)]
+struct Timeline<'a> {
+    reindeer: Vec<&'a str>,
+    positions: Vec<u32>,
+    points: Vec<u32>,
+    lead_changes: Vec<LeadChange<'a>>,
+    /// Standings after every second, only kept if requested
+    seconds: Vec<Second<'a>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Second<'a> {
+    time: u32,
+    positions: Vec<u32>,
+    points: Vec<u32>,
+    leaders: Vec<&'a str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LeadChange<'a> {
+    time: u32,
+    leaders: Vec<&'a str>,
+}
+
+impl<'a> Timeline<'a> {
+    fn race(reindeer: &'a [Reindeer], duration: u32, keep_seconds: bool) -> Self {
+        let mut positions = vec![0; reindeer.len()];
+        let mut points = vec![0; reindeer.len()];
+        let mut seconds = vec![];
+        let mut lead_changes = Vec::<LeadChange>::new();
+        let mut leaders = vec![];
+
+        for time in 1..=duration {
+            for (position, r) in positions.iter_mut().zip(reindeer) {
+                if r.flying(time - 1) {
+                    *position += r.speed;
+                }
+            }
+            let furthest = *positions.iter().max().expect("the race needs reindeer");
+            leaders.clear();
+            for (idx, r) in reindeer.iter().enumerate() {
+                if positions[idx] == furthest {
+                    points[idx] += 1;
+                    leaders.push(r.name.as_str());
+                }
+            }
+
+            if lead_changes
+                .last()
+                .is_none_or(|change| change.leaders != leaders)
+            {
+                lead_changes.push(LeadChange {
+                    time,
+                    leaders: leaders.clone(),
+                });
+            }
+            if keep_seconds {
+                seconds.push(Second {
+                    time,
+                    positions: positions.clone(),
+                    points: points.clone(),
+                    leaders: leaders.clone(),
+                });
+            }
+        }
+
+        Self {
+            reindeer: reindeer.iter().map(|r| r.name.as_str()).collect(),
+            positions,
+            points,
+            lead_changes,
+            seconds,
+        }
     }
 
-    fn cycle_length(&self) -> u32 {
-        self.flying_time + self.resting_time
+    /// JSON for a `.json` path, CSV with a row per reindeer and second otherwise
+    fn export(&self, path: &str) {
+        let file = std::fs::File::create(path).expect("Should have been able to create the file");
+        let mut writer = BufWriter::new(file);
+        if path.ends_with(".json") {
+            serde_json::to_writer(&mut writer, self).unwrap();
+        } else {
+            writeln!(writer, "time,reindeer,position,points,leading").unwrap();
+            for second in &self.seconds {
+                for (idx, name) in self.reindeer.iter().enumerate() {
+                    writeln!(
+                        writer,
+                        "{},{name},{},{},{}",
+                        second.time,
+                        second.positions[idx],
+                        second.points[idx],
+                        second.leaders.contains(name)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        writer.flush().unwrap();
     }
 }
@@ -1,113 +1,240 @@
 use itertools::Itertools;
 use parse_display::FromStr;
-use rayon::prelude::*;
 
+use crate::params;
+
+const TEASPOONS: i64 = 100;
+const CALORIES: i64 = 500;
+
+/// Supports `--set teaspoons=N` instead of 100 teaspoons
 pub(crate) fn part_1(input: &str) -> String {
-    let ingredients = input
-        .lines()
-        .map(|l| l.parse::<Ingredient>().unwrap())
-        .collect_vec();
-    ingredients
-        .iter()
-        .skip(1)
-        .map(|_| (0..=100))
-        .multi_cartesian_product()
-        .filter_map(|mut d| {
-            let sum = d.iter().sum::<i64>();
-            if sum <= 100 {
-                d.push(100 - sum);
-                Some(d)
-            } else {
-                None
-            }
-        })
-        .collect_vec()
-        .into_par_iter()
-        .map(|distribution| {
-            let scores = ingredients
-                .iter()
-                .zip(distribution)
-                .map(|(ingredient, amount)| {
-                    (
-                        ingredient.capacity * amount,
-                        ingredient.durability * amount,
-                        ingredient.flavor * amount,
-                        ingredient.texture * amount,
-                    )
-                })
-                .fold((0, 0, 0, 0), |acc: (i64, i64, i64, i64), curr| {
-                    (
-                        acc.0 + curr.0,
-                        acc.1 + curr.1,
-                        acc.2 + curr.2,
-                        acc.3 + curr.3,
-                    )
-                });
-            scores.0.max(0) * scores.1.max(0) * scores.2.max(0) * scores.3.max(0)
-        })
-        .max()
-        .unwrap()
-        .to_string()
+    let ingredients = parse_ingredients(input);
+    let teaspoons = params::get("teaspoons").unwrap_or(TEASPOONS);
+    best_recipe(&ingredients, teaspoons, None).to_string()
 }
 
+/// Supports `--set calories=N` instead of 500 calories
 pub(crate) fn part_2(input: &str) -> String {
-    let ingredients = input
-        .lines()
-        .map(|l| l.parse::<Ingredient>().unwrap())
-        .collect_vec();
-    ingredients
-        .iter()
-        .skip(1)
-        .map(|_| (0..=100))
-        .multi_cartesian_product()
-        .filter_map(|mut d| {
-            let sum = d.iter().sum::<i64>();
-            if sum <= 100 {
-                d.push(100 - sum);
-                Some(d)
-            } else {
-                None
-            }
-        })
-        .collect_vec()
-        .into_par_iter()
-        .filter_map(|distribution| {
-            let scores = ingredients
-                .iter()
-                .zip(distribution)
-                .map(|(ingredient, amount)| {
-                    (
-                        ingredient.capacity * amount,
-                        ingredient.durability * amount,
-                        ingredient.flavor * amount,
-                        ingredient.texture * amount,
-                        ingredient.calories * amount,
-                    )
-                })
-                .fold((0, 0, 0, 0, 0), |acc: (i64, i64, i64, i64, i64), curr| {
-                    (
-                        acc.0 + curr.0,
-                        acc.1 + curr.1,
-                        acc.2 + curr.2,
-                        acc.3 + curr.3,
-                        acc.4 + curr.4,
-                    )
-                });
-            (scores.4 == 500)
-                .then_some(scores.0.max(0) * scores.1.max(0) * scores.2.max(0) * scores.3.max(0))
-        })
-        .max()
-        .unwrap()
-        .to_string()
+    let ingredients = parse_ingredients(input);
+    let teaspoons = params::get("teaspoons").unwrap_or(TEASPOONS);
+    let calories = params::get("calories").unwrap_or(CALORIES);
+    best_recipe(&ingredients, teaspoons, Some(calories)).to_string()
+}
+
+fn parse_ingredients(input: &str) -> Vec<Ingredient> {
+    input.lines().map(|l| l.parse().unwrap()).collect()
+}
+
+fn best_recipe(ingredients: &[Ingredient], teaspoons: i64, calories: Option<i64>) -> i64 {
+    let mut search = Search::new(ingredients, calories);
+    search.seed(teaspoons);
+    search.search(0, teaspoons, [0; 5]);
+    let (score, amounts) = search.best.expect("no recipe matches the calories");
+    log::debug!(
+        "Recipe after {} branches: {}",
+        search.branches,
+        amounts
+            .iter()
+            .zip(ingredients)
+            .filter(|(&amount, _)| amount > 0)
+            .map(|(amount, ingredient)| format!("{amount} {}", ingredient.name))
+            .join(", ")
+    );
+    score
 }
 
 #[derive(Debug, Clone, FromStr)]
-#[display("{_name}: capacity {capacity}, durability {durability}, flavor {flavor}, texture {texture}, calories {calories}")]
+#[display("{name}: capacity {capacity}, durability {durability}, flavor {flavor}, texture {texture}, calories {calories}")]
 struct Ingredient {
-    _name: String,
+    name: String,
     capacity: i64,
     durability: i64,
     flavor: i64,
     texture: i64,
     calories: i64,
 }
+
+impl Ingredient {
+    /// The four scored properties followed by the calories
+    fn properties(&self) -> [i64; 5] {
+        [
+            self.capacity,
+            self.durability,
+            self.flavor,
+            self.texture,
+            self.calories,
+        ]
+    }
+}
+
+fn score(sums: &[i64; 5]) -> i64 {
+    sums[..4].iter().map(|&s| s.max(0)).product()
+}
+
+/// Branch and bound over the amount of each ingredient, the last one takes what's left.
+/// A branch is cut once even the best value of every property on its own, with all remaining
+/// teaspoons, can't beat the best recipe so far, so the result is optimal.
+#[derive(Debug, Clone)]
+struct Search {
+    properties: Vec<[i64; 5]>,
+    calories: Option<i64>,
+    /// Largest and smallest value of each property among the ingredients from an index on
+    max_from: Vec<[i64; 5]>,
+    min_from: Vec<[i64; 5]>,
+    amounts: Vec<i64>,
+    best: Option<(i64, Vec<i64>)>,
+    /// Weights for the AM-GM bound, the inverse property sums of the best recipe
+    weights: [f64; 4],
+    branches: u64,
+}
+
+impl Search {
+    fn new(ingredients: &[Ingredient], calories: Option<i64>) -> Self {
+        assert!(!ingredients.is_empty(), "a recipe needs ingredients");
+        let properties = ingredients.iter().map(Ingredient::properties).collect_vec();
+        let suffix = |pick: fn(i64, i64) -> i64| {
+            let mut res = properties.clone();
+            for idx in (0..res.len() - 1).rev() {
+                res[idx] = std::array::from_fn(|p| pick(res[idx][p], res[idx + 1][p]));
+            }
+            res
+        };
+        Self {
+            max_from: suffix(i64::max),
+            min_from: suffix(i64::min),
+            amounts: vec![0; properties.len()],
+            properties,
+            calories,
+            best: None,
+            weights: [1.0; 4],
+            branches: 0,
+        }
+    }
+
+    /// Hill-climb to a local optimum without the calorie limit, moving teaspoons between two
+    /// ingredients with shrinking step sizes. It gives the weights for the AM-GM bound and, if
+    /// calories don't matter, a good recipe to beat from the start.
+    fn seed(&mut self, teaspoons: i64) {
+        let n = self.properties.len() as i64;
+        let mut amounts = (0..n)
+            .map(|idx| teaspoons / n + i64::from(idx < teaspoons % n))
+            .collect_vec();
+        // recipes with some properties at zero or below still need a direction to improve
+        let key = |amounts: &[i64]| {
+            let sums = amounts
+                .iter()
+                .enumerate()
+                .fold([0; 5], |sums, (idx, &amount)| self.add(sums, idx, amount));
+            let positive = sums[..4].iter().filter(|&&s| s > 0).collect_vec();
+            (positive.len(), positive.into_iter().product::<i64>(), sums)
+        };
+
+        let mut step = (teaspoons / n).max(1);
+        let mut current = key(&amounts);
+        while step > 0 {
+            let best_move = (0..amounts.len())
+                .cartesian_product(0..amounts.len())
+                .filter(|&(from, to)| from != to && amounts[from] >= step)
+                .map(|(from, to)| {
+                    let mut next = amounts.clone();
+                    next[from] -= step;
+                    next[to] += step;
+                    (key(&next), next)
+                })
+                .max_by_key(|(key, _)| (key.0, key.1));
+            match best_move {
+                Some((key, next)) if (key.0, key.1) > (current.0, current.1) => {
+                    amounts = next;
+                    current = key;
+                }
+                _ => step /= 2,
+            }
+        }
+
+        let sums = current.2;
+        log::trace!(
+            "Hill-climbed to {amounts:?} with a score of {}",
+            score(&sums)
+        );
+        self.weights = std::array::from_fn(|p| 1.0 / sums[p].max(1) as f64);
+        if self.calories.is_none() {
+            self.best = Some((score(&sums), amounts));
+        }
+    }
+
+    fn search(&mut self, idx: usize, remaining: i64, sums: [i64; 5]) {
+        self.branches += 1;
+        if idx == self.properties.len() - 1 {
+            self.amounts[idx] = remaining;
+            let sums = self.add(sums, idx, remaining);
+            if self.calories.is_some_and(|c| sums[4] != c) {
+                return;
+            }
+            let score = score(&sums);
+            if self.best.as_ref().is_none_or(|best| score > best.0) {
+                self.best = Some((score, self.amounts.clone()));
+                self.weights = std::array::from_fn(|p| 1.0 / sums[p].max(1) as f64);
+            }
+            return;
+        }
+
+        if let Some(calories) = self.calories {
+            let least = sums[4] + remaining * self.min_from[idx][4];
+            let most = sums[4] + remaining * self.max_from[idx][4];
+            if calories < least || calories > most {
+                return;
+            }
+        }
+        if let Some((best, _)) = self.best {
+            let bound = std::array::from_fn(|p| sums[p] + remaining * self.max_from[idx][p]);
+            if score(&bound) <= best || self.weighted_bound(idx, remaining, &sums) < best as f64 {
+                return;
+            }
+        }
+
+        for amount in (0..=remaining).rev() {
+            self.amounts[idx] = amount;
+            let sums = self.add(sums, idx, amount);
+            self.search(idx + 1, remaining - amount, sums);
+        }
+    }
+
+    /// By AM-GM, the product of the properties is at most (Σ w·x / 4)⁴ / Π w for any positive
+    /// weights. The weighted sum is linear, so it's largest when the remaining teaspoons go to
+    /// a single ingredient, or to a mix of two that hits the calories exactly.
+    fn weighted_bound(&self, idx: usize, remaining: i64, sums: &[i64; 5]) -> f64 {
+        let weighted =
+            |values: &[i64; 5]| -> f64 { (0..4).map(|p| self.weights[p] * values[p] as f64).sum() };
+        let per_teaspoon = self.properties[idx..]
+            .iter()
+            .map(|properties| (weighted(properties), properties[4] as f64))
+            .collect_vec();
+        let best_mix = match self.calories {
+            _ if remaining == 0 => 0.0,
+            None => per_teaspoon.iter().map(|p| p.0).fold(f64::MIN, f64::max),
+            Some(calories) => {
+                let target = (calories - sums[4]) as f64 / remaining as f64;
+                per_teaspoon
+                    .iter()
+                    .cartesian_product(&per_teaspoon)
+                    .filter(|(low, high)| low.1 <= target && target <= high.1)
+                    .map(|(low, high)| {
+                        if high.1 == low.1 {
+                            low.0.max(high.0)
+                        } else {
+                            let share = (high.1 - target) / (high.1 - low.1);
+                            share * low.0 + (1.0 - share) * high.0
+                        }
+                    })
+                    .fold(f64::MIN, f64::max)
+            }
+        };
+        let most = best_mix * remaining as f64 + weighted(sums);
+        // a little slack for rounding errors
+        (most.max(0.0) / 4.0).powi(4) / self.weights.iter().product::<f64>() * (1.0 + 1e-9)
+    }
+
+    fn add(&self, sums: [i64; 5], idx: usize, amount: i64) -> [i64; 5] {
+        std::array::from_fn(|p| sums[p] + self.properties[idx][p] * amount)
+    }
+}
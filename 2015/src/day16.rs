@@ -1,83 +1,173 @@
 use std::collections::HashMap;
 
+use itertools::Itertools;
 use nom::{
+    branch::alt,
     bytes::complete::tag,
-    character::complete::{alpha1, u16, u8},
+    character::complete::{alpha1, space0, u16, u8},
+    combinator::all_consuming,
     multi::separated_list1,
     sequence::{delimited, terminated, tuple},
     Finish, IResult,
 };
 
+use crate::params;
+
+/// The MFCSAM readout in the query format, with the ranges of the outdated retroencabulator
+/// replacing some of the values in part 2
+const READOUT: &str = "children: 3, cats: 7, samoyeds: 2, pomeranians: 3, akitas: 0, vizslas: 0,
+goldfish: 5, trees: 3, cars: 2, perfumes: 1
+
+part 2:
+cats > 7, trees > 3, pomeranians < 3, goldfish < 5";
+
+/// Heading of the constraints that replace others in part 2
+const PART_2: &str = "part 2:";
+
+/// Supports `--set query=path` to read the readout from a file, with `:` or `==` for exact
+/// values, `<` and `>` for ranges, separated by commas or lines. Constraints after a
+/// `part 2:` line replace the ones before it in part 2.
 pub(crate) fn part_1(input: &str) -> String {
-    let search = HashMap::from([
-        ("children", 3),
-        ("cats", 7),
-        ("samoyeds", 2),
-        ("pomeranians", 3),
-        ("akitas", 0),
-        ("vizslas", 0),
-        ("goldfish", 5),
-        ("trees", 3),
-        ("cars", 2),
-        ("perfumes", 1),
-    ]);
-
-    input
-        .lines()
-        .map(parse_sue)
-        .find_map(|(id, properties)| {
-            properties
-                .iter()
-                .all(|(prop_name, prop_val)| search.get(prop_name) == Some(prop_val))
-                .then_some(id)
-        })
-        .unwrap()
-        .to_string()
+    let query = read_query();
+    let (readout, _) = split_query(&query);
+    find_sue(input, &Query::from(readout))
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    use Compare::*;
-    let search = HashMap::from([
-        ("children", Equal(3)),
-        ("cats", Greater(7)),
-        ("samoyeds", Equal(2)),
-        ("pomeranians", Less(3)),
-        ("akitas", Equal(0)),
-        ("vizslas", Equal(0)),
-        ("goldfish", Less(5)),
-        ("trees", Greater(3)),
-        ("cars", Equal(2)),
-        ("perfumes", Equal(1)),
-    ]);
-
-    input
+    let query = read_query();
+    let (readout, replaced) = split_query(&query);
+    if replaced.is_none() {
+        log::warn!("The query has no {PART_2} section, using the same constraints as part 1");
+    }
+    find_sue(
+        input,
+        &Query::from(readout).replace(Query::from(replaced.unwrap_or_default())),
+    )
+}
+
+fn read_query() -> String {
+    params::get::<String>("query").map_or(READOUT.to_owned(), |path| {
+        std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("Should have been able to read {path}: {err}"))
+    })
+}
+
+/// Constraints for both parts and the ones that replace them in part 2
+fn split_query(query: &str) -> (&str, Option<&str>) {
+    match query.split_once(PART_2) {
+        Some((readout, replaced)) => (readout, Some(replaced)),
+        None => (query, None),
+    }
+}
+
+/// Ids of the Sues that violate no constraint and match the most properties, best first
+fn find_sue(input: &str, query: &Query) -> String {
+    let ranking = input
         .lines()
         .map(parse_sue)
-        .find_map(|(id, properties)| {
-            properties
-                .iter()
-                .all(|(prop_name, prop_val)| match search.get(prop_name) {
-                    Some(Less(val)) => prop_val < val,
-                    Some(Equal(val)) => prop_val == val,
-                    Some(Greater(val)) => prop_val > val,
-                    None => false,
-                })
-                .then_some(id)
-        })
-        .unwrap()
-        .to_string()
+        .filter_map(|(id, properties)| query.matches(&properties).map(|count| (count, id)))
+        .sorted_by(|a, b| b.cmp(a))
+        .collect_vec();
+    for (count, id) in ranking.iter().take(SHORTLIST) {
+        log::debug!("Sue {id} matches {count} properties");
+    }
+
+    let best = ranking.first().expect("no Sue matches the readout").0;
+    let shortlist = ranking
+        .iter()
+        .take_while(|(count, _)| *count == best)
+        .map(|(_, id)| id)
+        .sorted()
+        .collect_vec();
+    if shortlist.len() > 1 {
+        log::warn!("{} Sues match {best} properties", shortlist.len());
+    }
+    shortlist.iter().join(", ")
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Number of candidates logged at debug level
+const SHORTLIST: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Compare {
     Less(u8),
     Equal(u8),
     Greater(u8),
 }
 
+impl Compare {
+    fn holds(&self, value: u8) -> bool {
+        match *self {
+            Compare::Less(val) => value < val,
+            Compare::Equal(val) => value == val,
+            Compare::Greater(val) => value > val,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Query<'a> {
+    constraints: HashMap<&'a str, Compare>,
+}
+
+impl<'a> Query<'a> {
+    /// Number of properties that satisfy a constraint, None if one violates its constraint.
+    /// Properties the query doesn't mention are unknown, so they don't count either way.
+    fn matches(&self, properties: &HashMap<&str, u8>) -> Option<usize> {
+        let mut count = 0;
+        for (name, value) in properties {
+            match self.constraints.get(name) {
+                Some(compare) if compare.holds(*value) => count += 1,
+                Some(_) => return None,
+                None => {}
+            }
+        }
+        Some(count)
+    }
+
+    /// The constraints of `other` take the place of the ones on the same properties
+    fn replace(mut self, other: Query<'a>) -> Self {
+        self.constraints.extend(other.constraints);
+        self
+    }
+}
+
+impl<'a> From<&'a str> for Query<'a> {
+    fn from(value: &'a str) -> Self {
+        fn parse(input: &str) -> IResult<&str, (&str, Compare)> {
+            let (input, name) = alpha1(input)?;
+            let (input, compare) = delimited(
+                space0,
+                alt((tag("=="), tag(":"), tag("="), tag("<"), tag(">"))),
+                space0,
+            )(input)?;
+            let (input, val) = u8(input)?;
+            let compare = match compare {
+                "<" => Compare::Less(val),
+                ">" => Compare::Greater(val),
+                _ => Compare::Equal(val),
+            };
+            Ok((input, (name, compare)))
+        }
+
+        let constraints = value
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                all_consuming(parse)(c)
+                    .finish()
+                    .unwrap_or_else(|err| panic!("Invalid constraint {c:?}: {err}"))
+                    .1
+            })
+            .collect();
+        Self { constraints }
+    }
+}
+
 fn parse_sue(input: &str) -> (u16, HashMap<&str, u8>) {
     type ParseRes<'a> = (u16, Vec<(&'a str, u8)>);
-    fn parse(input: &str) -> IResult<&str, ParseRes> {
+    fn parse(input: &str) -> IResult<&str, ParseRes<'_>> {
         tuple((
             delimited(tag("Sue "), u16, tag(": ")),
             separated_list1(tag(", "), tuple((terminated(alpha1, tag(": ")), u8))),
@@ -1,42 +1,129 @@
-pub(crate) fn part_1(input: &str) -> String {
-    let sizes = input
-        .lines()
-        .map(|l| l.parse::<u8>().unwrap())
-        .collect::<Vec<_>>();
-    let target = if sizes.len() == 5 { 25 } else { 150 };
+use itertools::Itertools;
+
+use crate::params;
+
+const TARGET: usize = 150;
 
-    find_possibilities(&sizes, target, vec![]).len().to_string()
+/// Supports `--set target=N`, inputs can also start with a `target: N` line
+pub(crate) fn part_1(input: &str) -> String {
+    let containers = Containers::from(input);
+    containers.counts().iter().sum::<u128>().to_string()
 }
 
+/// Supports `--set list=N` to log the first N combinations with the fewest containers
 pub(crate) fn part_2(input: &str) -> String {
-    let sizes = input
-        .lines()
-        .map(|l| l.parse::<u8>().unwrap())
-        .collect::<Vec<_>>();
-    let target = if sizes.len() == 5 { 25 } else { 150 };
-    let possibilities = find_possibilities(&sizes, target, vec![]);
-    let min = possibilities.iter().map(|p| p.len()).min().unwrap();
-
-    possibilities
-        .iter()
-        .filter(|p| p.len() == min)
-        .count()
-        .to_string()
+    let containers = Containers::from(input);
+    let counts = containers.counts();
+    let Some(fewest) = counts.iter().position(|&c| c > 0) else {
+        return 0.to_string();
+    };
+    log::debug!("At least {fewest} containers are needed");
+
+    if let Some(count) = params::get("list") {
+        for combination in containers.combinations(fewest).take(count) {
+            log::info!("{}", combination.iter().join(" + "));
+        }
+    }
+    counts[fewest].to_string()
+}
+
+#[derive(Debug, Clone)]
+struct Containers {
+    sizes: Vec<usize>,
+    target: usize,
 }
 
-fn find_possibilities(sizes: &[u8], target: u8, current: Vec<u8>) -> Vec<Vec<u8>> {
-    if target == 0 {
-        return vec![current];
+impl From<&str> for Containers {
+    fn from(value: &str) -> Self {
+        let mut target = params::get("target");
+        let mut sizes = vec![];
+        for line in value.lines() {
+            match line.strip_prefix("target: ") {
+                Some(t) => target = target.or(Some(t.parse().unwrap())),
+                None => sizes.push(line.parse().unwrap()),
+            }
+        }
+        Self {
+            sizes,
+            target: target.unwrap_or(TARGET),
+        }
     }
+}
 
-    sizes
-        .iter()
-        .enumerate()
-        .filter(|(_, s)| **s <= target)
-        .flat_map(|(i, s)| {
-            let mut current = current.clone();
-            current.push(*s);
-            find_possibilities(&sizes[i + 1..], target - s, current)
+impl Containers {
+    /// Number of combinations that hold exactly the target, by number of containers used
+    fn counts(&self) -> Vec<u128> {
+        // ways[k][v]: combinations of k containers with a volume of v
+        let mut ways = vec![vec![0_u128; self.target + 1]; self.sizes.len() + 1];
+        ways[0][0] = 1;
+        for (idx, &size) in self.sizes.iter().enumerate() {
+            if size > self.target {
+                continue;
+            }
+            for k in (0..=idx).rev() {
+                for v in (0..=self.target - size).rev() {
+                    if ways[k][v] > 0 {
+                        ways[k + 1][v + size] = ways[k + 1][v + size]
+                            .checked_add(ways[k][v])
+                            .expect("too many combinations to count");
+                    }
+                }
+            }
+        }
+        ways.iter().map(|w| w[self.target]).collect()
+    }
+
+    /// Lazily enumerates the combinations of `count` containers that hold exactly the target
+    fn combinations(&self, count: usize) -> impl Iterator<Item = Vec<usize>> + '_ {
+        // possible[idx][k][v]: k of the containers from idx on can hold v
+        let n = self.sizes.len();
+        let mut possible = vec![vec![vec![false; self.target + 1]; count + 1]; n + 1];
+        possible[n][0][0] = true;
+        for idx in (0..n).rev() {
+            let size = self.sizes[idx];
+            for k in 0..=count {
+                for v in 0..=self.target {
+                    possible[idx][k][v] = possible[idx + 1][k][v]
+                        || (k > 0 && v >= size && possible[idx + 1][k - 1][v - size]);
+                }
+            }
+        }
+
+        // depth-first search that only follows branches which lead to a combination
+        let mut stack = vec![];
+        if possible[0][count][self.target] {
+            stack.push((0, count, self.target, vec![]));
+        }
+        std::iter::from_fn(move || {
+            while let Some((start, k, v, chosen)) = stack.pop() {
+                if k == 0 {
+                    return Some(chosen);
+                }
+                for idx in (start..n).rev() {
+                    let size = self.sizes[idx];
+                    if size <= v && possible[idx + 1][k - 1][v - size] {
+                        let mut chosen = chosen.clone();
+                        chosen.push(size);
+                        stack.push((idx + 1, k - 1, v - size, chosen));
+                    }
+                }
+            }
+            None
         })
-        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containers_larger_than_the_target_are_skipped() {
+        let containers = Containers {
+            sizes: vec![20, 15, 10, 5, 5, 30],
+            target: 25,
+        };
+        assert_eq!(containers.counts(), [0, 0, 3, 1, 0, 0, 0]);
+        assert_eq!(containers.combinations(2).count(), 3);
+    }
 }
@@ -1,95 +1,424 @@
-use std::borrow::Cow;
+use std::{fmt::Display, str::FromStr};
 
 use itertools::Itertools;
 
+use crate::params;
+
+const STEPS: usize = 100;
+
+/// Supports `--set rule=B36/S23`, `--set topology=torus`, `--set steps=N`, `--set load=path.rle`
+/// to start from an RLE pattern and `--set save=path.rle` to save the final generation
 pub(crate) fn part_1(input: &str) -> String {
-    let grid = input
-        .lines()
-        .map(|l| l.as_bytes().iter().map(|&c| c == b'#').collect_vec())
-        .collect_vec();
-    log::debug!("grid:\n{}", display_gol_grid(&grid));
-
-    itertools::iterate(grid, |g| step(g, false))
-        .skip(1)
-        .take(100)
-        .last()
-        .unwrap()
-        .iter()
-        .map(|row| row.iter().filter(|c| **c).count())
-        .sum::<usize>()
-        .to_string()
+    let grid = load(input);
+    let automaton = Automaton::from_params(&grid, vec![]);
+    run(&automaton, grid, "part_1")
 }
 
+/// The corners are stuck on, `--set pinned=row,col;row,col` pins other cells instead
 pub(crate) fn part_2(input: &str) -> String {
-    let mut grid = input
-        .lines()
-        .map(|l| l.as_bytes().iter().map(|&c| c == b'#').collect_vec())
-        .collect_vec();
-    let row_len = grid.len();
-    let col_len = grid[0].len();
-    grid[0][0] = true;
-    grid[0][col_len - 1] = true;
-    grid[row_len - 1][0] = true;
-    grid[row_len - 1][col_len - 1] = true;
-
-    itertools::iterate(grid, |g| step(g, true))
-        .skip(1)
-        .take(100)
-        .last()
-        .unwrap()
-        .iter()
-        .map(|row| row.iter().filter(|c| **c).count())
-        .sum::<usize>()
-        .to_string()
-}
-
-fn step(grid: &[Vec<bool>], corner_correction: bool) -> Vec<Vec<bool>> {
-    let row_len = grid.len();
-    let col_len = grid[0].len();
-    let mut next = vec![vec![false; col_len]; row_len];
-
-    if corner_correction {
-        next[0][0] = true;
-        next[0][col_len - 1] = true;
-        next[row_len - 1][0] = true;
-        next[row_len - 1][col_len - 1] = true;
-    }
-
-    for row in 0..grid.len() {
-        for col in 0..grid[row].len() {
-            let count = neighbours((row, col), row_len, col_len)
-                .filter(|pos| grid[pos.0][pos.1])
-                .count();
-            if count == 3 || (count == 2 && grid[row][col]) {
-                next[row][col] = true;
+    let grid = load(input);
+    let pinned = match params::get::<String>("pinned") {
+        Some(cells) => cells
+            .split(';')
+            .map(|cell| {
+                let (row, col) = cell.split_once(',').expect("cells should be row,col");
+                (row.trim().parse().unwrap(), col.trim().parse().unwrap())
+            })
+            .collect(),
+        None => grid.corners(),
+    };
+    let automaton = Automaton::from_params(&grid, pinned);
+    run(&automaton, grid, "part_2")
+}
+
+fn load(input: &str) -> Grid {
+    let grid = match params::get::<String>("load") {
+        Some(path) => std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("Should have been able to read {path}: {err}"))
+            .parse(),
+        None => input.parse(),
+    }
+    .unwrap_or_else(|err| panic!("Invalid pattern: {err}"));
+    log::debug!("grid:\n{grid}");
+    grid
+}
+
+fn run(automaton: &Automaton, grid: Grid, suffix: &str) -> String {
+    let grid = automaton.run(grid, params::get("steps").unwrap_or(STEPS));
+    if let Some(path) = params::get_path("save", suffix) {
+        std::fs::write(&path, grid.to_rle(&automaton.rule))
+            .expect("Should have been able to save the pattern");
+        log::info!("Saved pattern to {path}");
+    }
+    grid.count().to_string()
+}
+
+/// Life-like rule such as `B3/S23`, as bitmasks of the neighbour counts
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Rule {
+    birth: u16,
+    survival: u16,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rule = Rule {
+            birth: 0,
+            survival: 0,
+        };
+        for part in s.trim().split('/') {
+            let (mask, counts) = match part.as_bytes().first() {
+                Some(b'B' | b'b') => (&mut rule.birth, &part[1..]),
+                Some(b'S' | b's') => (&mut rule.survival, &part[1..]),
+                _ => return Err(format!("expected B or S in {s:?}")),
+            };
+            for c in counts.chars() {
+                match c.to_digit(10) {
+                    Some(n) if n <= 8 => *mask |= 1 << n,
+                    _ => return Err(format!("invalid neighbour count {c:?} in {s:?}")),
+                }
+            }
+        }
+        Ok(rule)
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = |mask: u16| (0..=8).filter(|n| mask & 1 << n != 0).join("");
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, parse_display::FromStr)]
+#[display(style = "lowercase")]
+enum Topology {
+    /// Everything outside of the grid is dead
+    Bounded,
+    /// Opposite edges are neighbours
+    Torus,
+}
+
+impl Topology {
+    /// Rows above and below `row`, if there are any
+    fn neighbour_rows(&self, row: usize, height: usize) -> (Option<usize>, Option<usize>) {
+        match self {
+            Topology::Bounded => (row.checked_sub(1), (row + 1 < height).then_some(row + 1)),
+            Topology::Torus => (Some((row + height - 1) % height), Some((row + 1) % height)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Automaton {
+    rule: Rule,
+    topology: Topology,
+    /// Cells that are always alive
+    pinned: Vec<(usize, usize)>,
+}
+
+impl Automaton {
+    /// The rule defaults to the one of an RLE pattern, or Conway's B3/S23
+    fn from_params(grid: &Grid, pinned: Vec<(usize, usize)>) -> Self {
+        for &(row, col) in &pinned {
+            assert!(
+                row < grid.height && col < grid.width,
+                "pinned cell {row},{col} is outside of the grid"
+            );
+        }
+        Self {
+            rule: params::get("rule").or(grid.rule).unwrap_or(Rule {
+                birth: 1 << 3,
+                survival: 1 << 2 | 1 << 3,
+            }),
+            topology: params::get("topology").unwrap_or(Topology::Bounded),
+            pinned,
+        }
+    }
+
+    fn run(&self, mut grid: Grid, steps: usize) -> Grid {
+        self.pin(&mut grid);
+        for _ in 0..steps {
+            grid = self.step(&grid);
+            self.pin(&mut grid);
+        }
+        grid
+    }
+
+    fn pin(&self, grid: &mut Grid) {
+        for &(row, col) in &self.pinned {
+            grid.set(row, col, true);
+        }
+    }
+
+    /// Next generation, 64 cells at a time by adding up the neighbours in bit slices
+    fn step(&self, grid: &Grid) -> Grid {
+        let mut next = Grid::new(grid.width, grid.height);
+        let empty = vec![0; grid.words];
+        let row = |idx: Option<usize>| idx.map_or(&empty[..], |idx| grid.row(idx));
+        for r in 0..grid.height {
+            let (above, below) = self.topology.neighbour_rows(r, grid.height);
+            let rows = [row(above), grid.row(r), row(below)];
+            let shifted = rows.map(|cells| {
+                (
+                    grid.shift_west(cells, self.topology),
+                    grid.shift_east(cells, self.topology),
+                )
+            });
+
+            let alive = grid.row(r);
+            for w in 0..grid.words {
+                let neighbours = [
+                    shifted[0].0[w],
+                    rows[0][w],
+                    shifted[0].1[w],
+                    shifted[1].0[w],
+                    shifted[1].1[w],
+                    shifted[2].0[w],
+                    rows[2][w],
+                    shifted[2].1[w],
+                ];
+                // bits of the neighbour count, each word holds one bit of 64 counts
+                let mut count = [0_u64; 4];
+                for mut carry in neighbours {
+                    for bit in &mut count {
+                        let sum = *bit ^ carry;
+                        carry &= *bit;
+                        *bit = sum;
+                    }
+                }
+
+                let mut cells = 0;
+                for n in 0..=8 {
+                    let matches = (0..4).fold(!0, |acc, b| {
+                        acc & if n >> b & 1 == 1 { count[b] } else { !count[b] }
+                    });
+                    if self.rule.birth & 1 << n != 0 {
+                        cells |= matches & !alive[w];
+                    }
+                    if self.rule.survival & 1 << n != 0 {
+                        cells |= matches & alive[w];
+                    }
+                }
+                next.cells[r * grid.words + w] = cells & grid.word_mask(w);
+            }
+        }
+        next
+    }
+}
+
+/// Rows of bits, bits past the width of a row are always zero
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Grid {
+    width: usize,
+    height: usize,
+    words: usize,
+    cells: Vec<u64>,
+    /// Rule given in the header of an RLE pattern
+    rule: Option<Rule>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        let words = width.div_ceil(64);
+        Self {
+            width,
+            height,
+            words,
+            cells: vec![0; words * height],
+            rule: None,
+        }
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.cells[row * self.words..(row + 1) * self.words]
+    }
+
+    fn get(&self, row: usize, col: usize) -> bool {
+        self.cells[row * self.words + col / 64] >> (col % 64) & 1 == 1
+    }
+
+    fn set(&mut self, row: usize, col: usize, alive: bool) {
+        let word = &mut self.cells[row * self.words + col / 64];
+        if alive {
+            *word |= 1 << (col % 64);
+        } else {
+            *word &= !(1 << (col % 64));
+        }
+    }
+
+    fn count(&self) -> u32 {
+        self.cells.iter().map(|w| w.count_ones()).sum()
+    }
+
+    fn corners(&self) -> Vec<(usize, usize)> {
+        let (last_row, last_col) = (self.height - 1, self.width - 1);
+        vec![(0, 0), (0, last_col), (last_row, 0), (last_row, last_col)]
+    }
+
+    /// Bits of word `w` that are inside of the grid
+    fn word_mask(&self, w: usize) -> u64 {
+        match self.width - w * 64 {
+            bits if bits >= 64 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// Every cell gets the value of its western neighbour
+    fn shift_west(&self, row: &[u64], topology: Topology) -> Vec<u64> {
+        let mut res = (0..self.words)
+            .map(|w| row[w] << 1 | if w > 0 { row[w - 1] >> 63 } else { 0 })
+            .collect_vec();
+        if topology == Topology::Torus {
+            let last = self.width - 1;
+            res[0] |= row[last / 64] >> (last % 64) & 1;
+        }
+        res
+    }
+
+    /// Every cell gets the value of its eastern neighbour
+    fn shift_east(&self, row: &[u64], topology: Topology) -> Vec<u64> {
+        let mut res = (0..self.words)
+            .map(|w| row[w] >> 1 | row.get(w + 1).map_or(0, |next| next << 63))
+            .collect_vec();
+        if topology == Topology::Torus {
+            let last = self.width - 1;
+            res[last / 64] |= (row[0] & 1) << (last % 64);
+        }
+        res
+    }
+
+    /// Run length encoded, see <https://conwaylife.com/wiki/Run_Length_Encoded>
+    fn to_rle(&self, rule: &Rule) -> String {
+        let mut tokens = vec![];
+        // row the pattern is at after the tokens so far
+        let mut current = 0;
+        for row in 0..self.height {
+            let runs = (0..self.width)
+                .map(|col| self.get(row, col))
+                .dedup_with_count()
+                .collect_vec();
+            let runs = match runs.last() {
+                Some((_, false)) => &runs[..runs.len() - 1],
+                _ => &runs[..],
+            };
+            if runs.is_empty() {
+                continue;
+            }
+            if row > current {
+                tokens.push(run_token(row - current, '$'));
+                current = row;
             }
+            tokens.extend(
+                runs.iter()
+                    .map(|&(len, alive)| run_token(len, if alive { 'o' } else { 'b' })),
+            );
         }
+        tokens.push("!".to_owned());
+
+        let mut res = format!("x = {}, y = {}, rule = {rule}\n", self.width, self.height);
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len + token.len() > 70 {
+                res.push('\n');
+                line_len = 0;
+            }
+            line_len += token.len();
+            res.push_str(&token);
+        }
+        res.push('\n');
+        res
     }
-    next
 }
 
-fn neighbours(
-    pos: (usize, usize),
-    row_len: usize,
-    col_len: usize,
-) -> impl Iterator<Item = (usize, usize)> {
-    let rows = [pos.0.saturating_sub(1), pos.0, pos.0 + 1]
-        .into_iter()
-        .dedup()
-        .filter(move |&row| row < row_len);
-    let cols = [pos.1.saturating_sub(1), pos.1, pos.1 + 1]
-        .into_iter()
-        .dedup()
-        .filter(move |&col| col < col_len);
-    rows.cartesian_product(cols).filter(move |&p| p != pos)
+fn run_token(len: usize, tag: char) -> String {
+    if len == 1 {
+        tag.to_string()
+    } else {
+        format!("{len}{tag}")
+    }
 }
 
-fn display_gol_grid(grid: &[Vec<bool>]) -> String {
-    display_grid_with_map(grid, |&t| if t { "#".into() } else { ".".into() })
+/// Either rows of `#` and `.`, or an RLE pattern
+impl FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.lines().all(|l| l.bytes().all(|c| c == b'#' || c == b'.')) {
+            let rows = s.lines().collect_vec();
+            let mut grid = Grid::new(rows.first().map_or(0, |r| r.len()), rows.len());
+            for (row, line) in rows.iter().enumerate() {
+                for (col, c) in line.bytes().enumerate() {
+                    grid.set(row, col, c == b'#');
+                }
+            }
+            return Ok(grid);
+        }
+
+        let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+        let header = lines.next().ok_or("empty pattern")?;
+        // header like `x = 3, y = 3, rule = B3/S23`
+        let mut width = None;
+        let mut height = None;
+        let mut rule = None;
+        for field in header.split(',') {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("invalid header field {field:?}"))?;
+            let value = value.trim();
+            match key.trim() {
+                "x" => width = value.parse::<usize>().ok(),
+                "y" => height = value.parse::<usize>().ok(),
+                "rule" => rule = Some(value.parse::<Rule>()?),
+                _ => {}
+            }
+        }
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(format!("header {header:?} lacks a size"));
+        };
+
+        let mut grid = Grid::new(width, height);
+        grid.rule = rule;
+        let (mut row, mut col, mut len) = (0, 0, 0);
+        for c in lines.flat_map(str::chars) {
+            if let Some(digit) = c.to_digit(10) {
+                len = len * 10 + digit as usize;
+                continue;
+            }
+            let count = std::mem::take(&mut len).max(1);
+            match c {
+                'b' => col += count,
+                'o' => {
+                    for _ in 0..count {
+                        if row >= height || col >= width {
+                            return Err(format!("cell {row},{col} is outside of the pattern"));
+                        }
+                        grid.set(row, col, true);
+                        col += 1;
+                    }
+                }
+                '$' => {
+                    row += count;
+                    col = 0;
+                }
+                '!' => return Ok(grid),
+                c if c.is_whitespace() => {}
+                _ => return Err(format!("unexpected {c:?} in pattern")),
+            }
+        }
+        Ok(grid)
+    }
 }
 
-fn display_grid_with_map<T>(grid: &[Vec<T>], map: fn(&T) -> Cow<'static, str>) -> String {
-    grid.iter()
-        .map(|row| row.iter().map(map).join(""))
-        .join("\n")
+impl Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = (0..self.height).map(|row| {
+            (0..self.width)
+                .map(|col| if self.get(row, col) { '#' } else { '.' })
+                .collect::<String>()
+        });
+        write!(f, "{}", rows.format("\n"))
+    }
 }
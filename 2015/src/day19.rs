@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use itertools::Itertools;
 use regex::Regex;
 
@@ -6,34 +8,47 @@ pub(crate) fn part_1(input: &str) -> String {
     neighbours(molecule, &replacements).count().to_string()
 }
 
+/// Logs the derivation at debug level
 pub(crate) fn part_2(input: &str) -> String {
     let (replacements, molecule) = get_input(input);
+    let grammar = Grammar::new(&replacements);
+    let tree = grammar
+        .parse("e", molecule)
+        .expect("the molecule can't be made from e");
 
-    let mut remaining = molecule.to_owned();
-    remaining = remaining.replace("Rn", "(");
-    remaining = remaining.replace('Y', ",");
-    remaining = remaining.replace("Ar", ")");
-    for el in get_elements(&replacements) {
-        remaining = remaining.replace(&el, "0");
+    let mut frontier = vec![&tree];
+    let mut steps = 0;
+    while let Some(idx) = frontier.iter().position(|t| matches!(t, Tree::Node(..))) {
+        let Tree::Node(rule, children) = frontier[idx] else {
+            unreachable!()
+        };
+        frontier.splice(idx..=idx, children);
+        steps += 1;
+        let (from, to) = replacements[*rule];
+        log::debug!(
+            "{steps:>4}: {from} => {to}: {}",
+            frontier
+                .iter()
+                .map(|t| match t {
+                    Tree::Element(el) => grammar.names[*el],
+                    Tree::Node(rule, _) => replacements[*rule].0,
+                })
+                .join("")
+        );
     }
+    steps.to_string()
+}
 
-    let mut remaining = remaining
-        .as_bytes()
-        .iter()
-        .map(|c| match c {
-            b'(' => Value::Start,
-            b',' => Value::Sep,
-            b')' => Value::End,
-            v => Value::Num(v - b'0'),
-        })
+pub(crate) fn part_2_symbol_count(input: &str) -> String {
+    let (_, molecule) = get_input(input);
+    let el_regex = Regex::new(r"[A-Z][a-z]?").unwrap();
+    let symbols = el_regex
+        .find_iter(molecule)
+        .map(|m| m.as_str())
         .collect_vec();
+    let count = |el| symbols.iter().filter(|&&s| s == el).count();
 
-    // Alternatively just count(NumSymbols) - count(Rn) - count(Ar) - 2 * count(Y) - 1
-    while remaining.len() > 1 {
-        remaining = fold(remaining);
-    }
-
-    remaining[0].value().unwrap().to_string()
+    (symbols.len() - count("Rn") - count("Ar") - 2 * count("Y") - 1).to_string()
 }
 
 fn neighbours<'a>(
@@ -52,84 +67,233 @@ fn neighbours<'a>(
         .dedup()
 }
 
+/// How a replacement was applied to a part of the molecule
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tree {
+    Element(usize),
+    /// Index of the replacement and what its elements turned into
+    Node(usize, Vec<Tree>),
+}
+
+/// Parse result for a symbol over a span of the molecule
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Value {
-    Num(u8),
-    Start,
-    Sep,
-    End,
+struct Entry {
+    /// Replacements needed
+    cost: usize,
+    back: Back,
 }
 
-impl Value {
-    fn value(&self) -> Option<u8> {
-        match self {
-            Self::Num(v) => Some(*v),
-            _ => None,
-        }
-    }
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Back {
+    Element,
+    Unit {
+        rule: usize,
+        child: usize,
+    },
+    /// `rule` is None for the symbols in the middle of a replacement
+    Split {
+        mid: usize,
+        left: usize,
+        right: usize,
+        rule: Option<usize>,
+    },
 }
 
-fn fold(items: Vec<Value>) -> Vec<Value> {
-    let items = items
-        .into_iter()
-        .coalesce(|a, b| match (a, b) {
-            (Value::Num(a), Value::Num(b)) => Ok(Value::Num(a + b + 1)),
-            _ => Err((a, b)),
-        })
-        .collect_vec();
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Pair {
+    parent: usize,
+    /// Set if the pair completes the replacement
+    rule: Option<usize>,
+}
 
-    let mut new = vec![];
-    let mut it = items.iter().enumerate();
+/// The replacements as a grammar in which a replacement is split into a chain of pairs, so
+/// a weighted CYK parser finds the derivation with the fewest replacements
+#[derive(Debug, Clone)]
+struct Grammar<'a> {
+    /// Elements and the helper symbols of the pair chains, which have no name
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+    /// Parent, child and replacement of single element replacements
+    units: Vec<(usize, usize, usize)>,
+    pairs: HashMap<(usize, usize), Vec<Pair>>,
+}
 
-    while let Some((i, _)) = it.next() {
-        if i + 3 < items.len() && items[i + 1] == Value::Start && items[i + 3] == Value::End {
-            if let Value::Num(v1) = items[i] {
-                if let Value::Num(v2) = items[i + 2] {
-                    new.push(Value::Num(v1 + v2 + 1));
-                    it = it.dropping(3);
-                    continue;
+impl<'a> Grammar<'a> {
+    fn new(replacements: &[(&'a str, &'a str)]) -> Self {
+        let mut grammar = Self {
+            names: vec![],
+            ids: HashMap::new(),
+            units: vec![],
+            pairs: HashMap::new(),
+        };
+        for (rule, &(from, to)) in replacements.iter().enumerate() {
+            let parent = grammar.symbol(from);
+            let children = elements(to).map(|el| grammar.symbol(el)).collect_vec();
+            match children[..] {
+                [] => panic!("replacement {from} => {to} is empty"),
+                [child] => grammar.units.push((parent, child, rule)),
+                [first, ref rest @ ..] => {
+                    let mut left = first;
+                    for (idx, &right) in rest.iter().enumerate() {
+                        let pair = if idx + 1 == rest.len() {
+                            Pair {
+                                parent,
+                                rule: Some(rule),
+                            }
+                        } else {
+                            grammar.names.push("");
+                            Pair {
+                                parent: grammar.names.len() - 1,
+                                rule: None,
+                            }
+                        };
+                        grammar.pairs.entry((left, right)).or_default().push(pair);
+                        left = pair.parent;
+                    }
                 }
             }
         }
+        grammar
+    }
 
-        if i + 5 < items.len()
-            && items[i + 1] == Value::Start
-            && items[i + 3] == Value::Sep
-            && items[i + 5] == Value::End
-        {
-            if let Value::Num(v1) = items[i] {
-                if let Value::Num(v2) = items[i + 2] {
-                    if let Value::Num(v3) = items[i + 4] {
-                        new.push(Value::Num(v1 + v2 + v3 + 1));
-                        it = it.dropping(5);
-                        continue;
+    fn symbol(&mut self, name: &'a str) -> usize {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.names.len() - 1
+        })
+    }
+
+    /// Derivation of `molecule` from `start` with the fewest replacements
+    fn parse(&self, start: &str, molecule: &str) -> Option<Tree> {
+        let elements = elements(molecule)
+            .map(|el| self.ids.get(el).copied())
+            .collect::<Option<Vec<_>>>()?;
+        let n = elements.len();
+        // chart[i * (n + 1) + j]: symbols that can become elements i..j
+        let mut chart = vec![HashMap::<usize, Entry>::new(); (n + 1) * (n + 1)];
+        let span = |i: usize, j: usize| i * (n + 1) + j;
+
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                let mut entries = HashMap::new();
+                let relax = |entries: &mut HashMap<usize, Entry>, symbol, entry: Entry| {
+                    let current = entries.entry(symbol).or_insert(entry);
+                    if entry.cost < current.cost {
+                        *current = entry;
                     }
-                }
-            }
-        }
+                };
 
-        if i + 7 < items.len()
-            && items[i + 1] == Value::Start
-            && items[i + 3] == Value::Sep
-            && items[i + 5] == Value::Sep
-            && items[i + 7] == Value::End
-        {
-            if let Value::Num(v1) = items[i] {
-                if let Value::Num(v2) = items[i + 2] {
-                    if let Value::Num(v3) = items[i + 4] {
-                        if let Value::Num(v4) = items[i + 6] {
-                            new.push(Value::Num(v1 + v2 + v3 + v4 + 1));
-                            it = it.dropping(7);
-                            continue;
+                if len == 1 {
+                    let back = Back::Element;
+                    relax(&mut entries, elements[i], Entry { cost: 0, back });
+                }
+                for mid in i + 1..j {
+                    for (&left, l) in &chart[span(i, mid)] {
+                        for (&right, r) in &chart[span(mid, j)] {
+                            for &Pair { parent, rule } in
+                                self.pairs.get(&(left, right)).into_iter().flatten()
+                            {
+                                let cost = l.cost + r.cost + usize::from(rule.is_some());
+                                let back = Back::Split {
+                                    mid,
+                                    left,
+                                    right,
+                                    rule,
+                                };
+                                relax(&mut entries, parent, Entry { cost, back });
+                            }
                         }
                     }
                 }
+                // replacements of a single element can chain, so repeat until nothing improves
+                loop {
+                    let before = entries.clone();
+                    for &(parent, child, rule) in &self.units {
+                        if let Some(c) = before.get(&child) {
+                            let back = Back::Unit { rule, child };
+                            relax(
+                                &mut entries,
+                                parent,
+                                Entry {
+                                    cost: c.cost + 1,
+                                    back,
+                                },
+                            );
+                        }
+                    }
+                    if entries == before {
+                        break;
+                    }
+                }
+                chart[span(i, j)] = entries;
             }
         }
 
-        new.push(items[i]);
+        let start = *self.ids.get(start)?;
+        let entry = chart[span(0, n)].get(&start)?;
+        log::debug!("{} replacements from {}", entry.cost, self.names[start]);
+        Some(self.tree(&chart, n, 0, n, start))
     }
-    new
+
+    fn tree(
+        &self,
+        chart: &[HashMap<usize, Entry>],
+        n: usize,
+        i: usize,
+        j: usize,
+        symbol: usize,
+    ) -> Tree {
+        match chart[i * (n + 1) + j][&symbol].back {
+            Back::Element => Tree::Element(symbol),
+            Back::Unit { rule, child } => Tree::Node(rule, vec![self.tree(chart, n, i, j, child)]),
+            Back::Split {
+                mid,
+                left,
+                right,
+                rule: Some(rule),
+            } => {
+                let mut children = self.chain(chart, n, i, mid, left);
+                children.push(self.tree(chart, n, mid, j, right));
+                Tree::Node(rule, children)
+            }
+            Back::Split { rule: None, .. } => unreachable!("helper symbols are part of a chain"),
+        }
+    }
+
+    /// Children of a replacement from the helper symbol for its first elements
+    fn chain(
+        &self,
+        chart: &[HashMap<usize, Entry>],
+        n: usize,
+        i: usize,
+        j: usize,
+        symbol: usize,
+    ) -> Vec<Tree> {
+        match chart[i * (n + 1) + j][&symbol].back {
+            Back::Split {
+                mid,
+                left,
+                right,
+                rule: None,
+            } => {
+                let mut children = self.chain(chart, n, i, mid, left);
+                children.push(self.tree(chart, n, mid, j, right));
+                children
+            }
+            _ => vec![self.tree(chart, n, i, j, symbol)],
+        }
+    }
+}
+
+/// Elements like `Ca`, or `e`
+fn elements(molecule: &str) -> impl Iterator<Item = &str> {
+    let el_regex = Regex::new(r"[A-Z][a-z]*|[a-z]+").unwrap();
+    el_regex
+        .find_iter(molecule)
+        .map(|m| m.as_str())
+        .collect_vec()
+        .into_iter()
 }
 
 fn get_input(input: &str) -> (Vec<(&str, &str)>, &str) {
@@ -140,24 +304,3 @@ fn get_input(input: &str) -> (Vec<(&str, &str)>, &str) {
         .collect();
     (replacements, molecule)
 }
-
-fn get_elements(replacements: &[(&str, &str)]) -> Vec<String> {
-    let el_regex = Regex::new(r"[A-Z][a-z]?").unwrap();
-    replacements
-        .iter()
-        .flat_map(|r| [r.0, r.1])
-        .flat_map(|r| {
-            el_regex
-                .find_iter(r)
-                .map(|m| m.as_str().to_owned())
-                .collect_vec()
-        })
-        .filter(|el| !["Rn", "Y", "Ar"].contains(&el.as_str()))
-        .sorted_by(|a, b| match b.len().cmp(&a.len()) {
-            std::cmp::Ordering::Less => std::cmp::Ordering::Less,
-            std::cmp::Ordering::Equal => a.cmp(b),
-            std::cmp::Ordering::Greater => std::cmp::Ordering::Greater,
-        })
-        .dedup()
-        .collect()
-}
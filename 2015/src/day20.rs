@@ -1,23 +1,96 @@
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use crate::params;
 
+/// Fewest houses sieved at once
+const SEGMENT: u64 = 1 << 12;
+
+/// Supports `--set presents=N` and `--set limit=N` to change the elves
 pub(crate) fn part_1(input: &str) -> String {
-    let target: u64 = input.trim().parse().unwrap();
-    (target / 100..target / 10)
-        .into_par_iter()
-        .find_first(|num| sum_of_prime_factors(num, 1) * 10 >= target)
-        .unwrap()
-        .to_string()
+    let elves = Elves::from_params(10, None);
+    elves.first_house(input.trim().parse().unwrap()).to_string()
 }
 
+/// Supports `--set presents=N` and `--set limit=N` to change the elves
 pub(crate) fn part_2(input: &str) -> String {
-    let target: u64 = input.trim().parse().unwrap();
-    (target / 100..target / 10)
-        .into_par_iter()
-        .find_first(|num| sum_of_prime_factors(num, (num + 49) / 50) * 11 >= target)
-        .unwrap()
-        .to_string()
+    let elves = Elves::from_params(11, Some(50));
+    elves.first_house(input.trim().parse().unwrap()).to_string()
+}
+
+/// Every elf delivers `presents` times its number to each house it visits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Elves {
+    presents: u64,
+    /// Number of houses an elf visits before it stops, unlimited if None
+    limit: Option<u64>,
 }
 
-fn sum_of_prime_factors(num: &u64, start: u64) -> u64 {
-    (start..=num / 2).filter(|i| num % i == 0).sum::<u64>() + num
+impl Elves {
+    fn from_params(presents: u64, limit: Option<u64>) -> Self {
+        Self {
+            presents: params::get("presents").unwrap_or(presents),
+            limit: params::get("limit").or(limit),
+        }
+    }
+
+    /// First house that gets at least `target` presents
+    fn first_house(&self, target: u64) -> u64 {
+        assert!(
+            self.presents > 0 && self.limit != Some(0),
+            "the elves don't deliver any presents"
+        );
+        // house n gets at least n times the presents from elf n, so it's a bound for the answer
+        let most = target.div_ceil(self.presents).max(1);
+        // the divisors of n add up to less than (e^γ ln ln n + 0.6483 / ln ln n) n for n >= 3
+        // (Robin), which grows from n = 7 on, so no house below `least` can get enough presents
+        // even without a limit. Few houses are sieved right away, which also covers small n.
+        let least = if most > SEGMENT {
+            let ln_ln = (most as f64).ln().ln();
+            let factor = 1.781_072_418 * ln_ln + 0.6483 / ln_ln;
+            ((most as f64 / factor) as u64).clamp(1, most)
+        } else {
+            1
+        };
+        // sieve a quarter of the houses below at a time, so little is sieved past the answer
+        let mut start = least;
+        loop {
+            let end = most.min(start + (start / 4).max(SEGMENT));
+            if let Some(house) = self.search(start, end, target) {
+                return house;
+            }
+            start = end + 1;
+        }
+    }
+
+    /// Sieves the presents of the houses from `start` up to `end`
+    fn search(&self, start: u64, end: u64, target: u64) -> Option<u64> {
+        let (start, end) = (start as usize, end as usize);
+        // a house gets less than its number times 1 + ln of it from the elves, keep that in u32
+        // to halve the memory the sieve runs through
+        assert!(
+            end.checked_mul(end.ilog2() as usize + 2)
+                .is_some_and(|most| most <= u32::MAX as usize),
+            "too many houses to sieve"
+        );
+        let mut sums = vec![0_u32; end - start + 1];
+        for elf in 1..=end {
+            let last = self
+                .limit
+                .map_or(end, |limit| end.min(elf.saturating_mul(limit as usize)));
+            let first = elf.max(start.div_ceil(elf) * elf);
+            if first > last {
+                continue;
+            }
+            for sum in sums[first - start..=last - start].iter_mut().step_by(elf) {
+                *sum += elf as u32;
+            }
+        }
+        let offset = sums
+            .iter()
+            .position(|&sum| u64::from(sum) * self.presents >= target)?;
+        let house = start + offset;
+        log::debug!(
+            "House {house} gets {} presents, searched from {start} up to {end}",
+            u64::from(sums[offset]) * self.presents
+        );
+        Some(house as u64)
+    }
 }
@@ -1,10 +1,156 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
+use itertools::Itertools;
+use parse_display::FromStr;
+
+const PLAYER_HIT_POINTS: u32 = 100;
+
+const WEAPONS: [Item; 5] = [
+    Item::new("Dagger", 8, 4, 0),
+    Item::new("Shortsword", 10, 5, 0),
+    Item::new("Warhammer", 25, 6, 0),
+    Item::new("Longsword", 40, 7, 0),
+    Item::new("Greataxe", 74, 8, 0),
+];
+
+const ARMOR: [Item; 5] = [
+    Item::new("Leather", 13, 0, 1),
+    Item::new("Chainmail", 31, 0, 2),
+    Item::new("Splintmail", 53, 0, 3),
+    Item::new("Bandedmail", 75, 0, 4),
+    Item::new("Platemail", 102, 0, 5),
+];
+
+const RINGS: [Item; 6] = [
+    Item::new("Damage +1", 25, 1, 0),
+    Item::new("Damage +2", 50, 2, 0),
+    Item::new("Damage +3", 100, 3, 0),
+    Item::new("Defense +1", 20, 0, 1),
+    Item::new("Defense +2", 40, 0, 2),
+    Item::new("Defense +3", 80, 0, 3),
+];
 
 pub(crate) fn part_1(input: &str) -> String {
-    "Not implemented!".to_string()
+    let boss = input.trim().parse::<Fighter>().unwrap();
+    let cheapest = loadouts()
+        .filter(|loadout| fight(loadout.equip(PLAYER_HIT_POINTS), boss, false))
+        .min_by_key(Loadout::cost)
+        .unwrap();
+
+    log::debug!("Cheapest winning loadout: {cheapest}");
+    fight(cheapest.equip(PLAYER_HIT_POINTS), boss, true);
+    cheapest.cost().to_string()
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    "Not implemented!".to_string()
+    let boss = input.trim().parse::<Fighter>().unwrap();
+    let priciest = loadouts()
+        .filter(|loadout| !fight(loadout.equip(PLAYER_HIT_POINTS), boss, false))
+        .max_by_key(Loadout::cost)
+        .unwrap();
+
+    log::debug!("Most expensive losing loadout: {priciest}");
+    fight(priciest.equip(PLAYER_HIT_POINTS), boss, true);
+    priciest.cost().to_string()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromStr)]
+#[display("Hit Points: {hit_points}\nDamage: {damage}\nArmor: {armor}")]
+struct Fighter {
+    hit_points: u32,
+    damage: u32,
+    armor: u32,
+}
+
+impl Fighter {
+    fn attack(&self, defender: &mut Fighter) -> u32 {
+        let damage = self.damage.saturating_sub(defender.armor).max(1);
+        defender.hit_points = defender.hit_points.saturating_sub(damage);
+        damage
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Item {
+    name: &'static str,
+    cost: u32,
+    damage: u32,
+    armor: u32,
+}
+
+impl Item {
+    const fn new(name: &'static str, cost: u32, damage: u32, armor: u32) -> Self {
+        Self {
+            name,
+            cost,
+            damage,
+            armor,
+        }
+    }
+}
+
+/// One weapon, up to one armor and up to two different rings
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Loadout(Vec<&'static Item>);
+
+impl Loadout {
+    fn cost(&self) -> u32 {
+        self.0.iter().map(|i| i.cost).sum()
+    }
+
+    fn equip(&self, hit_points: u32) -> Fighter {
+        Fighter {
+            hit_points,
+            damage: self.0.iter().map(|i| i.damage).sum(),
+            armor: self.0.iter().map(|i| i.armor).sum(),
+        }
+    }
+}
+
+impl std::fmt::Display for Loadout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.0.iter().map(|i| i.name).join(", ");
+        write!(f, "{names} (cost {})", self.cost())
+    }
+}
+
+fn loadouts() -> impl Iterator<Item = Loadout> {
+    let armor = std::iter::once(None).chain(ARMOR.iter().map(Some));
+    let rings = (0..=2).flat_map(|count| RINGS.iter().combinations(count));
+    WEAPONS
+        .iter()
+        .cartesian_product(armor)
+        .cartesian_product(rings.collect_vec())
+        .map(|((weapon, armor), rings)| {
+            let mut items = vec![weapon];
+            items.extend(armor);
+            items.extend(rings);
+            Loadout(items)
+        })
+}
+
+/// Fight until one side is dead, the player attacks first. Returns whether the player won.
+fn fight(mut player: Fighter, mut boss: Fighter, trace: bool) -> bool {
+    for turn in 1.. {
+        let damage = player.attack(&mut boss);
+        if trace {
+            log::debug!(
+                "Turn {turn}: player deals {damage} damage, boss goes down to {} hit points",
+                boss.hit_points
+            );
+        }
+        if boss.hit_points == 0 {
+            return true;
+        }
+
+        let damage = boss.attack(&mut player);
+        if trace {
+            log::debug!(
+                "Turn {turn}: boss deals {damage} damage, player goes down to {} hit points",
+                player.hit_points
+            );
+        }
+        if player.hit_points == 0 {
+            return false;
+        }
+    }
+    unreachable!()
 }
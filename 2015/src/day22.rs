@@ -1,10 +1,271 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+};
+
+use itertools::Itertools;
+use parse_display::FromStr;
+
+const PLAYER: Wizard = Wizard {
+    hit_points: 50,
+    mana: 500,
+};
+
+const SPELLS: [Spell; 5] = [
+    Spell::instant("Magic Missile", 53, 4, 0),
+    Spell::instant("Drain", 73, 2, 2),
+    Spell::effect(
+        "Shield",
+        113,
+        Effect {
+            turns: 6,
+            armor: 7,
+            damage: 0,
+            mana: 0,
+        },
+    ),
+    Spell::effect(
+        "Poison",
+        173,
+        Effect {
+            turns: 6,
+            armor: 0,
+            damage: 3,
+            mana: 0,
+        },
+    ),
+    Spell::effect(
+        "Recharge",
+        229,
+        Effect {
+            turns: 5,
+            armor: 0,
+            damage: 0,
+            mana: 101,
+        },
+    ),
+];
 
 pub(crate) fn part_1(input: &str) -> String {
-    "Not implemented!".to_string()
+    let boss = input.trim().parse::<Boss>().unwrap();
+    let victory = Duel::new(PLAYER, boss, &SPELLS, false).solve().unwrap();
+    log::debug!("Cheapest victory: {}", victory.display_spells());
+    victory.mana_spent.to_string()
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    "Not implemented!".to_string()
+    let boss = input.trim().parse::<Boss>().unwrap();
+    let victory = Duel::new(PLAYER, boss, &SPELLS, true).solve().unwrap();
+    log::debug!(
+        "Cheapest victory in hard mode: {}",
+        victory.display_spells()
+    );
+    victory.mana_spent.to_string()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Wizard {
+    hit_points: u32,
+    mana: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromStr)]
+#[display("Hit Points: {hit_points}\nDamage: {damage}")]
+struct Boss {
+    hit_points: u32,
+    damage: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Spell {
+    name: &'static str,
+    cost: u32,
+    damage: u32,
+    heal: u32,
+    effect: Option<Effect>,
+}
+
+impl Spell {
+    const fn instant(name: &'static str, cost: u32, damage: u32, heal: u32) -> Self {
+        Self {
+            name,
+            cost,
+            damage,
+            heal,
+            effect: None,
+        }
+    }
+
+    const fn effect(name: &'static str, cost: u32, effect: Effect) -> Self {
+        Self {
+            name,
+            cost,
+            damage: 0,
+            heal: 0,
+            effect: Some(effect),
+        }
+    }
+}
+
+/// Applied at the start of every turn while the timer runs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Effect {
+    turns: u8,
+    armor: u32,
+    damage: u32,
+    mana: u32,
+}
+
+/// A fight between a wizard and a boss with a given spell table.
+///
+/// In hard mode the wizard loses one hit point at the start of each of their turns.
+#[derive(Debug, Clone)]
+struct Duel<'a> {
+    player: Wizard,
+    boss: Boss,
+    spells: &'a [Spell],
+    hard: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct State {
+    player_hit_points: u32,
+    mana: u32,
+    boss_hit_points: u32,
+    timers: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Victory<'a> {
+    mana_spent: u32,
+    spells: Vec<&'a Spell>,
+}
+
+impl<'a> Duel<'a> {
+    fn new(player: Wizard, boss: Boss, spells: &'a [Spell], hard: bool) -> Self {
+        Self {
+            player,
+            boss,
+            spells,
+            hard,
+        }
+    }
+
+    /// Best-first search over the mana spent, returns the cheapest victory if there is one
+    fn solve(&self) -> Option<Victory<'a>> {
+        let start = State {
+            player_hit_points: self.player.hit_points,
+            mana: self.player.mana,
+            boss_hit_points: self.boss.hit_points,
+            timers: vec![0; self.spells.len()],
+        };
+
+        let mut queue = BinaryHeap::from([Reverse((0, start, vec![]))]);
+        let mut seen = HashSet::new();
+        while let Some(Reverse((mana_spent, state, cast))) = queue.pop() {
+            if state.boss_hit_points == 0 {
+                return Some(Victory {
+                    mana_spent,
+                    spells: cast.into_iter().map(|idx| &self.spells[idx]).collect(),
+                });
+            }
+            if !seen.insert(state.clone()) {
+                continue;
+            }
+
+            for (idx, next) in self.player_turns(&state) {
+                let mut cast = cast.clone();
+                let cost = idx.map_or(0, |idx| self.spells[idx].cost);
+                cast.extend(idx);
+                queue.push(Reverse((mana_spent + cost, next, cast)));
+            }
+        }
+        None
+    }
+
+    /// All states reachable by casting one spell and letting the boss answer, the player survives all of them.
+    /// No spell is cast if the effects already defeat the boss.
+    fn player_turns(&self, state: &State) -> Vec<(Option<usize>, State)> {
+        let mut state = state.clone();
+        if self.hard {
+            state.player_hit_points = state.player_hit_points.saturating_sub(1);
+            if state.player_hit_points == 0 {
+                return vec![];
+            }
+        }
+        self.apply_effects(&mut state);
+        if state.boss_hit_points == 0 {
+            return vec![(None, state)];
+        }
+
+        (0..self.spells.len())
+            .filter(|&idx| state.timers[idx] == 0 && self.spells[idx].cost <= state.mana)
+            .filter_map(|idx| {
+                let spell = &self.spells[idx];
+                let mut next = state.clone();
+                next.mana -= spell.cost;
+                next.boss_hit_points = next.boss_hit_points.saturating_sub(spell.damage);
+                next.player_hit_points += spell.heal;
+                if let Some(effect) = spell.effect {
+                    next.timers[idx] = effect.turns;
+                }
+                if next.boss_hit_points == 0 {
+                    return Some((Some(idx), next));
+                }
+
+                let armor = self.apply_effects(&mut next);
+                if next.boss_hit_points == 0 {
+                    return Some((Some(idx), next));
+                }
+                let damage = self.boss.damage.saturating_sub(armor).max(1);
+                next.player_hit_points = next.player_hit_points.saturating_sub(damage);
+                (next.player_hit_points > 0).then_some((Some(idx), next))
+            })
+            .collect()
+    }
+
+    /// Tick all running effects, returns the armor of the effects still active afterwards
+    fn apply_effects(&self, state: &mut State) -> u32 {
+        let mut armor = 0;
+        for (timer, spell) in state.timers.iter_mut().zip(self.spells) {
+            if let (Some(effect), 1..) = (spell.effect, *timer) {
+                state.boss_hit_points = state.boss_hit_points.saturating_sub(effect.damage);
+                state.mana += effect.mana;
+                *timer -= 1;
+                if *timer > 0 {
+                    armor += effect.armor;
+                }
+            }
+        }
+        armor
+    }
+}
+
+impl Victory<'_> {
+    fn display_spells(&self) -> String {
+        self.spells.iter().map(|s| s.name).join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_fights() {
+        let player = Wizard {
+            hit_points: 10,
+            mana: 250,
+        };
+        let fight = |hit_points| {
+            let boss = Boss {
+                hit_points,
+                damage: 8,
+            };
+            let victory = Duel::new(player, boss, &SPELLS, false).solve().unwrap();
+            (victory.mana_spent, victory.display_spells())
+        };
+        assert_eq!(fight(13), (226, "Poison, Magic Missile".to_owned()));
+        assert_eq!(fight(14).0, 641);
+    }
 }
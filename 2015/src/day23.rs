@@ -1,10 +1,241 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::i64,
+    combinator::map,
+    sequence::{preceded, separated_pair},
+    Finish, IResult,
+};
 
+use crate::params;
+
+const STEP_LIMIT: u64 = 10_000_000;
+
+/// Supports `--set a=N`, `--set b=N` for the initial registers and `--set limit=N` for the
+/// number of steps before giving up
 pub(crate) fn part_1(input: &str) -> String {
-    "Not implemented!".to_string()
+    run(input, 0, true)
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    "Not implemented!".to_string()
+    run(input, 1, true)
+}
+
+pub(crate) fn part_1_plain(input: &str) -> String {
+    run(input, 0, false)
+}
+
+pub(crate) fn part_2_plain(input: &str) -> String {
+    run(input, 1, false)
+}
+
+fn run(input: &str, a: u64, shortcut: bool) -> String {
+    let program = input.lines().map(Instruction::from).collect::<Vec<_>>();
+    let registers = [params::get("a").unwrap_or(a), params::get("b").unwrap_or(0)];
+    let limit = params::get("limit").unwrap_or(STEP_LIMIT);
+    let mut computer = Computer::new(&program, registers, Some(limit), shortcut);
+    let stop = computer.run();
+    log::debug!(
+        "{stop:?} after {} steps with registers {:?}",
+        computer.steps,
+        computer.registers
+    );
+    match stop {
+        Stop::Halted => computer.registers[Register::B as usize].to_string(),
+        Stop::StepLimit => {
+            log::warn!("Program did not halt within {limit} steps");
+            format!("No result, step limit of {limit} reached")
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Register {
+    A,
+    B,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Instruction {
+    Half(Register),
+    Triple(Register),
+    Increment(Register),
+    Jump(i64),
+    JumpIfEven(Register, i64),
+    JumpIfOne(Register, i64),
+}
+
+impl From<&str> for Instruction {
+    fn from(value: &str) -> Self {
+        fn register(input: &str) -> IResult<&str, Register> {
+            alt((
+                map(tag("a"), |_| Register::A),
+                map(tag("b"), |_| Register::B),
+            ))(input)
+        }
+
+        fn offset(input: &str) -> IResult<&str, i64> {
+            alt((preceded(tag("+"), i64), i64))(input)
+        }
+
+        fn parse(input: &str) -> IResult<&str, Instruction> {
+            alt((
+                map(preceded(tag("hlf "), register), Instruction::Half),
+                map(preceded(tag("tpl "), register), Instruction::Triple),
+                map(preceded(tag("inc "), register), Instruction::Increment),
+                map(preceded(tag("jmp "), offset), Instruction::Jump),
+                map(
+                    preceded(tag("jie "), separated_pair(register, tag(", "), offset)),
+                    |(r, o)| Instruction::JumpIfEven(r, o),
+                ),
+                map(
+                    preceded(tag("jio "), separated_pair(register, tag(", "), offset)),
+                    |(r, o)| Instruction::JumpIfOne(r, o),
+                ),
+            ))(input)
+        }
+
+        parse(value).finish().unwrap().1
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Stop {
+    Halted,
+    StepLimit,
+}
+
+/// Interpreter for the Turing Lock, with every step traced at trace level.
+///
+/// With `shortcut` set, the loop counting the Collatz steps of a register is
+/// detected and evaluated directly instead of being stepped through.
+#[derive(Debug, Clone)]
+struct Computer<'a> {
+    program: &'a [Instruction],
+    registers: [u64; 2],
+    pc: i64,
+    steps: u64,
+    limit: Option<u64>,
+    shortcut: bool,
+}
+
+impl<'a> Computer<'a> {
+    fn new(
+        program: &'a [Instruction],
+        registers: [u64; 2],
+        limit: Option<u64>,
+        shortcut: bool,
+    ) -> Self {
+        Self {
+            program,
+            registers,
+            pc: 0,
+            steps: 0,
+            limit,
+            shortcut,
+        }
+    }
+
+    fn run(&mut self) -> Stop {
+        while let Some(&instruction) = usize::try_from(self.pc)
+            .ok()
+            .and_then(|pc| self.program.get(pc))
+        {
+            if self.limit.is_some_and(|limit| self.steps >= limit) {
+                return Stop::StepLimit;
+            }
+            if self.shortcut && self.collatz_shortcut() {
+                continue;
+            }
+
+            log::trace!(
+                "{:>4}: {instruction:<28?} a={:<12} b={}",
+                self.pc,
+                self.registers[0],
+                self.registers[1]
+            );
+            self.step(instruction);
+            self.steps += 1;
+        }
+        Stop::Halted
+    }
+
+    fn step(&mut self, instruction: Instruction) {
+        let mut jump = 1;
+        match instruction {
+            Instruction::Half(r) => self.registers[r as usize] /= 2,
+            Instruction::Triple(r) => self.registers[r as usize] *= 3,
+            Instruction::Increment(r) => self.registers[r as usize] += 1,
+            Instruction::Jump(offset) => jump = offset,
+            Instruction::JumpIfEven(r, offset) => {
+                if self.registers[r as usize].is_multiple_of(2) {
+                    jump = offset;
+                }
+            }
+            Instruction::JumpIfOne(r, offset) => {
+                if self.registers[r as usize] == 1 {
+                    jump = offset;
+                }
+            }
+        }
+        self.pc += jump;
+    }
+
+    /// Recognise the loop
+    ///
+    /// ```text
+    /// jio x, +8
+    /// inc y
+    /// jie x, +4
+    /// tpl x
+    /// inc x
+    /// jmp +2
+    /// hlf x
+    /// jmp -7
+    /// ```
+    ///
+    /// which counts the Collatz steps of `x` into `y`, and jump past it. The steps the loop
+    /// takes are still counted, if they exceed the limit the loop is stepped through instead.
+    fn collatz_shortcut(&mut self) -> bool {
+        use Instruction::*;
+
+        let start = self.pc as usize;
+        let Some(
+            &[JumpIfOne(x, 8), Increment(y), JumpIfEven(x2, 4), Triple(x3), Increment(x4), Jump(2), Half(x5), Jump(-7)],
+        ) = self.program.get(start..start + 8)
+        else {
+            return false;
+        };
+        if x == y || [x2, x3, x4, x5].iter().any(|&r| r != x) || self.registers[x as usize] == 0 {
+            return false;
+        }
+
+        let mut value = self.registers[x as usize];
+        let mut count = 0;
+        // the final jio that leaves the loop
+        let mut steps = 1;
+        while value != 1 {
+            value = if value.is_multiple_of(2) {
+                steps += 5;
+                value / 2
+            } else {
+                steps += 7;
+                3 * value + 1
+            };
+            count += 1;
+        }
+        if self.limit.is_some_and(|limit| self.steps + steps > limit) {
+            return false;
+        }
+        log::debug!(
+            "Collatz loop at {start}: {x:?}={} takes {count} steps",
+            self.registers[x as usize]
+        );
+
+        self.registers[x as usize] = 1;
+        self.registers[y as usize] += count;
+        self.pc += 8;
+        self.steps += steps;
+        true
+    }
 }
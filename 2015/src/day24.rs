@@ -1,10 +1,118 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
+use itertools::Itertools;
 
 pub(crate) fn part_1(input: &str) -> String {
-    "Not implemented!".to_string()
+    balance(&parse_packages(input), 3).to_string()
 }
 
 pub(crate) fn part_2(input: &str) -> String {
-    "Not implemented!".to_string()
+    balance(&parse_packages(input), 4).to_string()
+}
+
+fn parse_packages(input: &str) -> Vec<u64> {
+    input
+        .lines()
+        .map(|l| l.parse::<u64>().unwrap())
+        .sorted_by(|a, b| b.cmp(a))
+        .collect()
+}
+
+/// Split the packages into `groups` groups of equal weight, with as few packages
+/// in the first group as possible, ties broken by the lowest quantum entanglement.
+/// Returns the quantum entanglement of the first group.
+fn balance(packages: &[u64], groups: u64) -> u64 {
+    let total = packages.iter().sum::<u64>();
+    assert_eq!(total % groups, 0, "packages can't be split evenly");
+    let target = total / groups;
+
+    // packages are sorted descending, so no smaller group can reach the target
+    let min_size = packages
+        .iter()
+        .scan(0, |sum, p| {
+            *sum += p;
+            Some(*sum)
+        })
+        .position(|sum| sum >= target)
+        .unwrap()
+        + 1;
+
+    for size in min_size..=packages.len() {
+        let candidates = (0..packages.len())
+            .combinations(size)
+            .filter(|c| c.iter().map(|&i| packages[i]).sum::<u64>() == target)
+            .map(|c| (c.iter().map(|&i| packages[i]).product::<u64>(), c))
+            .sorted();
+
+        for (entanglement, first) in candidates {
+            let remaining = (0..packages.len())
+                .filter(|i| !first.contains(i))
+                .map(|i| packages[i])
+                .collect_vec();
+            if let Some(rest) = partition(&remaining, groups - 1, target) {
+                let first = first.iter().map(|&i| packages[i]).collect_vec();
+                log::debug!("Group 1: {first:?} (quantum entanglement {entanglement})");
+                for (idx, group) in rest.iter().enumerate() {
+                    log::debug!("Group {}: {group:?}", idx + 2);
+                }
+                return entanglement;
+            }
+            log::trace!("{first:?} balances, but the remaining packages can't be split");
+        }
+    }
+    panic!("no balanced split exists")
+}
+
+/// Split descending `packages` into `groups` groups that all weigh `target`
+fn partition(packages: &[u64], groups: u64, target: u64) -> Option<Vec<Vec<u64>>> {
+    if groups == 1 {
+        return (packages.iter().sum::<u64>() == target).then(|| vec![packages.to_vec()]);
+    }
+    // a package heavier than a group can't go anywhere
+    if packages.first().is_none_or(|&p| p > target) {
+        return None;
+    }
+
+    // the heaviest package has to be somewhere, fixing it avoids trying permutations of groups
+    let mut chosen = vec![0];
+    find_subset(
+        packages,
+        target - packages[0],
+        1,
+        &mut chosen,
+        &mut |chosen| {
+            let remaining = (0..packages.len())
+                .filter(|i| !chosen.contains(i))
+                .map(|i| packages[i])
+                .collect_vec();
+            partition(&remaining, groups - 1, target).map(|mut rest| {
+                rest.insert(0, chosen.iter().map(|&i| packages[i]).collect());
+                rest
+            })
+        },
+    )
+}
+
+/// Depth-first search for index sets from `start` on that add up to `remaining`,
+/// until `accept` returns a result for one of them
+fn find_subset<T>(
+    packages: &[u64],
+    remaining: u64,
+    start: usize,
+    chosen: &mut Vec<usize>,
+    accept: &mut impl FnMut(&[usize]) -> Option<T>,
+) -> Option<T> {
+    if remaining == 0 {
+        return accept(chosen);
+    }
+
+    for idx in start..packages.len() {
+        if packages[idx] <= remaining {
+            chosen.push(idx);
+            let res = find_subset(packages, remaining - packages[idx], idx + 1, chosen, accept);
+            chosen.pop();
+            if res.is_some() {
+                return res;
+            }
+        }
+    }
+    None
 }
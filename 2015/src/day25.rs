@@ -1,10 +1,72 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
+use itertools::Itertools;
+use parse_display::FromStr;
 
+use crate::params;
+
+const FIRST_CODE: u64 = 20151125;
+const MULTIPLIER: u64 = 252533;
+const MODULUS: u64 = 33554393;
+
+/// Supports `--set corner=N` to render the top left N by N codes of the table
 pub(crate) fn part_1(input: &str) -> String {
-    "Not implemented!".to_string()
+    let position = input.trim().parse::<Position>().unwrap();
+    if let Some(size) = params::get("corner") {
+        log::info!("Top left corner:\n{}", render_corner(size));
+    }
+    position.code().to_string()
+}
+
+pub(crate) fn part_2(_input: &str) -> String {
+    "Start The Weather Machine!".to_string()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromStr)]
+#[display(
+    "To continue, please consult the code grid in the manual.  Enter the code at row {row}, column {column}."
+)]
+struct Position {
+    row: u64,
+    column: u64,
+}
+
+impl Position {
+    /// 1-based position of the code in the order the diagonals are filled
+    fn index(&self) -> u64 {
+        let diagonal = self.row + self.column - 1;
+        diagonal * (diagonal - 1) / 2 + self.column
+    }
+
+    fn code(&self) -> u64 {
+        FIRST_CODE * mod_pow(MULTIPLIER, self.index() - 1, MODULUS) % MODULUS
+    }
+}
+
+/// Square and multiply
+fn mod_pow(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut res = 1;
+    base %= modulus;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            res = res * base % modulus;
+        }
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+    res
 }
 
-pub(crate) fn part_2(input: &str) -> String {
-    "Not implemented!".to_string()
+fn render_corner(size: u64) -> String {
+    let width = size.to_string().len().max(2);
+    let header = format!(
+        "{:width$} |{}",
+        "",
+        (1..=size).map(|column| format!("{column:>10}")).join("")
+    );
+    let rows = (1..=size).map(|row| {
+        let codes = (1..=size)
+            .map(|column| format!("{:>10}", Position { row, column }.code()))
+            .join("");
+        format!("{row:>width$} |{codes}")
+    });
+    std::iter::once(header).chain(rows).join("\n")
 }
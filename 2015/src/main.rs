@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 
 mod day01;
 mod day02;
@@ -25,12 +25,19 @@ mod day22;
 mod day23;
 mod day24;
 mod day25;
+mod history;
+mod params;
+mod route;
+mod status;
 
 type ProblemFns = (fn(&str) -> String, fn(&str) -> String);
 
+/// Additional implementation of a part: day, part, name and solution
+type Alternative = (u8, u8, &'static str, fn(&str) -> String);
+
 const YEAR: u32 = 2015;
 
-const DAYS: [ProblemFns; 20] = [
+const DAYS: [ProblemFns; 25] = [
     (day01::part_1, day01::part_2),
     (day02::part_1, day02::part_2),
     (day03::part_1, day03::part_2),
@@ -51,15 +58,27 @@ const DAYS: [ProblemFns; 20] = [
     (day18::part_1, day18::part_2),
     (day19::part_1, day19::part_2),
     (day20::part_1, day20::part_2),
-    //(day21::part_1, day21::part_2),
-    //(day22::part_1, day22::part_2),
-    //(day23::part_1, day23::part_2),
-    //(day24::part_1, day24::part_2),
-    //(day25::part_1, day25::part_2),
+    (day21::part_1, day21::part_2),
+    (day22::part_1, day22::part_2),
+    (day23::part_1, day23::part_2),
+    (day24::part_1, day24::part_2),
+    (day25::part_1, day25::part_2),
+];
+
+const ALTERNATIVES: [Alternative; 5] = [
+    (10, 1, "direct", day10::part_1_direct),
+    (10, 2, "direct", day10::part_2_direct),
+    (19, 2, "symbol count", day19::part_2_symbol_count),
+    (23, 1, "plain interpreter", day23::part_1_plain),
+    (23, 2, "plain interpreter", day23::part_2_plain),
 ];
 
 #[derive(Parser, Debug)]
+#[command(after_help = params::help())]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The day which problem to run
     #[arg(short, long, default_value_t = DAYS.len() as u8, value_parser = clap::value_parser!(u8).range(1..=(DAYS.len() as i64)))]
     day: u8,
@@ -69,12 +88,45 @@ struct Args {
     all: bool,
 
     /// Use the example instead of the full input
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, global = true, default_value_t = false)]
     example: bool,
 
     /// Overwrite environment or default loglevel
     #[arg(short, long)]
     loglevel: Option<String>,
+
+    /// Append the timings of an --all run to the history file
+    #[arg(long, default_value_t = false, requires = "all")]
+    record: bool,
+
+    /// Run every implementation of each part, check that they agree and compare their runtimes
+    #[arg(short, long, default_value_t = false)]
+    compare: bool,
+
+    /// How often each implementation is run with --compare
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+
+    /// Set a parameter of a day, e.g. --set 4.zeros=7, can be repeated
+    #[arg(short = 's', long = "set", value_name = "[DAY.]KEY=VALUE", value_parser = params::parse_arg)]
+    params: Vec<(Option<u8>, String, String)>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show which days of this year are implemented, have inputs and pass their examples,
+    /// `status.sh` in the repository root shows all years
+    Status,
+    /// Show the recorded runtimes of a day across commits
+    History {
+        /// The day which runtimes to show
+        #[arg(short, long, value_parser = clap::value_parser!(u8).range(1..=(DAYS.len() as i64)))]
+        day: u8,
+
+        /// Relative runtime increase over the previous commit that counts as slower
+        #[arg(short, long, default_value_t = 1.2)]
+        threshold: f64,
+    },
 }
 
 fn main() {
@@ -86,26 +138,76 @@ fn main() {
         std::env::set_var("RUST_LOG", "info");
     }
     pretty_env_logger::init();
+    let days = if args.all {
+        (1..=DAYS.len() as u8).collect()
+    } else {
+        vec![args.day]
+    };
+    if let Err(error) = params::init(args.params.clone(), &days) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, error)
+            .exit();
+    }
+
+    #[cfg(feature = "embed-inputs")]
+    embedded::report_missing(args.example);
+
+    match args.command {
+        Some(Command::Status) => return status::print_status(),
+        Some(Command::History { day, threshold }) => {
+            return history::print_history(day, args.example, threshold)
+        }
+        None => {}
+    }
+
+    let run = |day| {
+        if args.compare {
+            compare_day(day, args.example, args.runs)
+        } else {
+            run_day(day, args.example)
+        }
+    };
 
     if args.all {
-        let duration = (1..=DAYS.len())
-            .map(|day| run_day(day as u8, args.example))
+        let timings = (1..=DAYS.len())
+            .map(|day| run(day as u8))
+            .collect::<Vec<_>>();
+        let duration = timings
+            .iter()
+            .flatten()
+            .flatten()
             .sum::<std::time::Duration>();
         log::info!("Total time for all days: {duration:?}");
+        if args.record {
+            history::append(&timings, args.example);
+        }
     } else {
-        run_day(args.day, args.example);
+        run(args.day);
     }
 }
 
-fn run_day(day: u8, example: bool) -> std::time::Duration {
-    let day_idx: usize = day.saturating_sub(1).into();
-    let (part_1, part_2) = DAYS[day_idx];
+fn try_read_input(day: u8, example: bool) -> Option<String> {
+    #[cfg(feature = "embed-inputs")]
+    if let Some(input) = embedded::get(day, example) {
+        return Some(input.to_owned());
+    }
+
     let directory = if example { "examples" } else { "input" };
     let input_path = format!("../{directory}/{YEAR}/{day}.txt");
-    let input =
-        std::fs::read_to_string(input_path).expect("Should have been able to read the file");
+    std::fs::read_to_string(input_path).ok()
+}
+
+/// None if the day was skipped because its input is missing
+fn run_day(day: u8, example: bool) -> Option<[std::time::Duration; 2]> {
+    let day_idx: usize = day.saturating_sub(1).into();
+    let (part_1, part_2) = DAYS[day_idx];
+    let Some(input) = try_read_input(day, example) else {
+        log::warn!("Skipping day {day}, no input found");
+        return None;
+    };
 
     log::info!("Selected day {day}");
+    params::select(day);
     log::info!("Running part 1...");
     let start = std::time::Instant::now();
     let res = (part_1)(&input);
@@ -119,5 +221,77 @@ fn run_day(day: u8, example: bool) -> std::time::Duration {
     log::info!("Done in {elapsed2:?}, Result: {res}");
     log::info!("Total time: {:?}", elapsed1 + elapsed2);
 
-    elapsed1 + elapsed2
+    Some([elapsed1, elapsed2])
+}
+
+/// None if the day was skipped because its input is missing
+fn compare_day(day: u8, example: bool, runs: u32) -> Option<[std::time::Duration; 2]> {
+    let day_idx: usize = day.saturating_sub(1).into();
+    let (part_1, part_2) = DAYS[day_idx];
+    let Some(input) = try_read_input(day, example) else {
+        log::warn!("Skipping day {day}, no input found");
+        return None;
+    };
+
+    log::info!("Selected day {day}");
+    params::select(day);
+    let mut timings = [std::time::Duration::ZERO; 2];
+    for (part, primary) in [(1, part_1), (2, part_2)] {
+        let implementations = std::iter::once(("primary", primary)).chain(
+            ALTERNATIVES
+                .iter()
+                .filter(|alt| alt.0 == day && alt.1 == part)
+                .map(|alt| (alt.2, alt.3)),
+        );
+
+        let mut results = vec![];
+        for (name, solution) in implementations {
+            log::info!("Running part {part} ({name}) {runs} times...");
+            let mut res = String::new();
+            let mut times = vec![];
+            for _ in 0..runs {
+                let start = std::time::Instant::now();
+                res = (solution)(&input);
+                times.push(start.elapsed());
+            }
+            let min = *times.iter().min().unwrap();
+            let mean = times.iter().sum::<std::time::Duration>() / runs;
+            results.push((name, res, min, mean));
+        }
+
+        let (_, expected, primary_min, _) = &results[0];
+        timings[usize::from(part) - 1] = *primary_min;
+        for (name, res, min, mean) in &results {
+            let speedup = primary_min.as_secs_f64() / min.as_secs_f64();
+            log::info!(
+                "Part {part} {name:>20}: min {min:>12?}, mean {mean:>12?}, {speedup:>8.2}x, Result: {res}"
+            );
+            assert_eq!(
+                res, expected,
+                "Implementation {name} of day {day} part {part} disagrees with the primary one"
+            );
+        }
+    }
+
+    Some(timings)
+}
+
+#[cfg(feature = "embed-inputs")]
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/embedded.rs"));
+
+    pub(crate) fn get(day: u8, example: bool) -> Option<&'static str> {
+        let embedded = if example { EXAMPLES } else { INPUTS };
+        embedded[usize::from(day) - 1]
+    }
+
+    pub(crate) fn report_missing(example: bool) {
+        let missing = (1..=super::DAYS.len() as u8)
+            .filter(|&day| get(day, example).is_none())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let kind = if example { "example" } else { "input" };
+            log::warn!("Built without {kind} for days {missing:?}, these are read from disk");
+        }
+    }
 }
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        OnceLock,
+    },
+};
+
+use itertools::Itertools;
+
+/// Parameters each day reads with a description, keys ending in `.` take any name after them
+const KEYS: &[(u8, &str, &str)] = &[
+    (4, "zeros", "leading zeros in part 2 (6)"),
+    (4, "start", "first number to check (0)"),
+    (4, "threads", "threads, 0 uses all cores (0)"),
+    (4, "checkpoint", "file to save and resume from"),
+    (6, "size", "side length of the grid (1000)"),
+    (6, "image", "path to render the lights to"),
+    (7, "wire.", "signal to override the wire with"),
+    (7, "dot", "path to export the circuit to"),
+    (10, "steps", "number of steps (40, 50)"),
+    (11, "list", "log the next N passwords"),
+    (11, "alphabet", "letters to use (a-z)"),
+    (11, "forbidden", "forbidden letters (iol)"),
+    (11, "straight", "length of the straight (3)"),
+    (11, "pairs", "number of pairs (2)"),
+    (12, "filter", "exclusions in part 2, split by ;"),
+    (14, "duration", "seconds of the race (2503)"),
+    (14, "timeline", "export path, .csv or .json"),
+    (15, "teaspoons", "teaspoons in a recipe (100)"),
+    (15, "calories", "calories in part 2 (500)"),
+    (16, "query", "file with the properties"),
+    (17, "target", "liters to store (150)"),
+    (17, "list", "log the first N combinations"),
+    (18, "rule", "rule like B3/S23"),
+    (18, "topology", "bounded or torus (bounded)"),
+    (18, "steps", "number of steps (100)"),
+    (18, "load", "grid to start from, plain or RLE"),
+    (18, "save", "path to save the grid to as RLE"),
+    (18, "pinned", "cells stuck on as row,col;..."),
+    (20, "presents", "presents per elf (10, 11)"),
+    (20, "limit", "houses per elf (none, 50)"),
+    (23, "a", "initial value of a (0, 1)"),
+    (23, "b", "initial value of b (0)"),
+    (23, "limit", "steps before giving up (1e7)"),
+    (25, "corner", "render the top left N by N codes"),
+];
+
+/// Parameters given with `--set [day.]key=value`, by day
+static PARAMS: OnceLock<HashMap<(u8, String), String>> = OnceLock::new();
+
+/// The day whose parameters are read
+static DAY: AtomicU8 = AtomicU8::new(0);
+
+/// Assign the parameters to days, a key without a day goes to the selected day that reads it.
+/// Fails if several selected days read such a key.
+pub(crate) fn init(params: Vec<(Option<u8>, String, String)>, days: &[u8]) -> Result<(), String> {
+    let mut resolved = HashMap::new();
+    for (day, key, value) in params {
+        let day = match day {
+            Some(day) => day,
+            None => {
+                let readers = days.iter().filter(|&&day| reads(day, &key)).collect_vec();
+                match readers[..] {
+                    [&day] => day,
+                    [] => {
+                        log::warn!("Ignoring parameter {key}, no selected day reads it");
+                        continue;
+                    }
+                    [&first, ..] => {
+                        return Err(format!(
+                        "parameter {key} is read by days {readers:?}, pick one like {first}.{key}"
+                    ))
+                    }
+                }
+            }
+        };
+        if !reads(day, &key) {
+            log::warn!("Ignoring parameter {day}.{key}, day {day} doesn't read it");
+            continue;
+        }
+        resolved.insert((day, key), value);
+    }
+    PARAMS
+        .set(resolved)
+        .expect("Parameters should only be set once");
+    Ok(())
+}
+
+/// Read the parameters of `day` from now on
+pub(crate) fn select(day: u8) {
+    DAY.store(day, Ordering::Relaxed);
+}
+
+pub(crate) fn get<T: FromStr>(key: &str) -> Option<T> {
+    let day = DAY.load(Ordering::Relaxed);
+    debug_assert!(
+        reads(day, key),
+        "Day {day} reads undeclared parameter {key}"
+    );
+    let value = PARAMS.get()?.get(&(day, key.to_owned()))?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => panic!("Invalid value {value:?} for parameter {day}.{key}"),
+    }
+}
+
+/// Path given by the parameter `key` with `_{suffix}` inserted before the extension, so that
+/// each part writes its own file
+pub(crate) fn get_path(key: &str, suffix: &str) -> Option<String> {
+    let path = get::<String>(key)?;
+    Some(match path.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains(['/', '\\']) => {
+            format!("{stem}_{suffix}.{extension}")
+        }
+        _ => format!("{path}_{suffix}"),
+    })
+}
+
+/// Names and values of the parameters of the selected day with keys starting with `prefix`
+pub(crate) fn prefixed(prefix: &str) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+    let day = DAY.load(Ordering::Relaxed);
+    debug_assert!(
+        reads(day, prefix),
+        "Day {day} reads undeclared parameter {prefix}"
+    );
+    PARAMS
+        .get()
+        .into_iter()
+        .flatten()
+        .filter(move |((d, _), _)| *d == day)
+        .filter_map(move |((_, k), v)| Some((k.strip_prefix(prefix)?, v.as_str())))
+}
+
+pub(crate) fn parse_arg(arg: &str) -> Result<(Option<u8>, String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected [day.]key=value, got {arg:?}"))?;
+    let (day, key) = match key.trim().split_once('.') {
+        Some((day, key)) if day.parse::<u8>().is_ok() => (Some(day.parse().unwrap()), key),
+        _ => (None, key.trim()),
+    };
+    Ok((day, key.to_owned(), value.trim().to_owned()))
+}
+
+/// List of all parameters for the help
+pub(crate) fn help() -> String {
+    let mut help =
+        "Parameters, the day can be left out if only one selected day reads the key:".to_owned();
+    for (day, key, description) in KEYS {
+        let name = if key.ends_with('.') { "NAME" } else { "" };
+        write!(
+            help,
+            "\n  {:<16}{description}",
+            format!("{day}.{key}{name}")
+        )
+        .unwrap();
+    }
+    help
+}
+
+fn reads(day: u8, key: &str) -> bool {
+    KEYS.iter().any(|&(d, k, _)| {
+        d == day && (k == key || k.ends_with('.') && key.starts_with(k) && key.len() > k.len())
+    })
+}
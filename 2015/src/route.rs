@@ -0,0 +1,230 @@
+//! Held-Karp dynamic programming over subsets of nodes, for Hamiltonian paths and cycles
+//! on complete graphs given as a distance matrix
+
+use itertools::Itertools;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Objective {
+    Shortest,
+    Longest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Route {
+    /// Visiting order, for cycles the return to the first node is implied
+    pub(crate) nodes: Vec<usize>,
+    pub(crate) length: i64,
+}
+
+/// Best path that visits every node exactly once, from any start to any end
+pub(crate) fn path(distances: &[Vec<i64>], objective: Objective) -> Route {
+    check_size(distances);
+    let nodes = (0..distances.len()).collect::<Vec<_>>();
+    let edge = |a: usize, b: usize| distances[a][b];
+    solve(&nodes, |_| 0, edge, |_| 0, objective)
+}
+
+/// Best closed tour through every node, starting and ending at node 0
+pub(crate) fn cycle(distances: &[Vec<i64>], objective: Objective) -> Route {
+    check_size(distances);
+    if distances.is_empty() {
+        return Route {
+            nodes: Vec::new(),
+            length: 0,
+        };
+    }
+    let rest = (1..distances.len()).collect::<Vec<_>>();
+    let edge = |a: usize, b: usize| distances[a][b];
+    let mut route = solve(&rest, |n| edge(0, n), edge, |n| edge(n, 0), objective);
+    route.nodes.insert(0, 0);
+    route
+}
+
+/// Most nodes a route is found through, the table of a path through this many takes
+/// 22·2²¹·4 bytes, about 185 MB
+const MAX_NODES: usize = 22;
+
+fn check_size(distances: &[Vec<i64>]) {
+    assert!(
+        distances.len() <= MAX_NODES,
+        "{} nodes are too many, routes through at most {MAX_NODES} can be found",
+        distances.len()
+    );
+}
+
+/// Order `nodes` so that entering the first one, the edges in between and leaving the last
+/// one cost the least, or the most for [`Objective::Longest`]
+fn solve(
+    nodes: &[usize],
+    enter: impl Fn(usize) -> i64,
+    edge: impl Fn(usize, usize) -> i64,
+    leave: impl Fn(usize) -> i64,
+    objective: Objective,
+) -> Route {
+    let n = nodes.len();
+    if n == 0 {
+        return Route {
+            nodes: Vec::new(),
+            length: 0,
+        };
+    }
+
+    // minimize the negated lengths to find the longest route, in an i32 to halve the table
+    let sign = match objective {
+        Objective::Shortest => 1,
+        Objective::Longest => -1,
+    };
+    let longest = (0..n)
+        .cartesian_product(0..n)
+        .map(|(i, j)| edge(nodes[i], nodes[j]))
+        .chain((0..n).flat_map(|i| [enter(nodes[i]), leave(nodes[i])]))
+        .map(i64::abs)
+        .max()
+        .unwrap();
+    assert!(
+        longest.saturating_mul(n as i64 + 1) < i64::from(i32::MAX),
+        "distances up to {longest} are too long to add up"
+    );
+    let enter = |i: usize| sign * enter(nodes[i]) as i32;
+    let edge = |i: usize, j: usize| sign * edge(nodes[i], nodes[j]) as i32;
+    let leave = |i: usize| sign * leave(nodes[i]) as i32;
+
+    // best[index(mask, last)]: visiting the nodes in mask, ending at last. The bit of last is
+    // always set, so it is left out of the index to halve the table.
+    let full = (1_usize << n) - 1;
+    let index = |mask: usize, last: usize| {
+        let low = mask & ((1 << last) - 1);
+        let high = (mask >> (last + 1)) << last;
+        (high | low) * n + last
+    };
+    let mut best = vec![i32::MAX; (1 << (n - 1)) * n];
+    for last in 0..n {
+        best[index(1 << last, last)] = enter(last);
+    }
+    for mask in 1..=full {
+        for last in bits(mask) {
+            let cost = best[index(mask, last)];
+            if cost == i32::MAX {
+                continue;
+            }
+            for next in bits(full & !mask) {
+                let entry = &mut best[index(mask | 1 << next, next)];
+                *entry = (*entry).min(cost + edge(last, next));
+            }
+        }
+    }
+
+    let (length, mut last) = (0..n)
+        .map(|last| (best[index(full, last)] + leave(last), last))
+        .min()
+        .unwrap();
+
+    // walk back through the table to find the predecessors
+    let mut order = vec![last];
+    let mut mask = full;
+    let mut cost = length - leave(last);
+    while mask != 1 << last {
+        let prev_mask = mask & !(1 << last);
+        let prev = bits(prev_mask)
+            .find(|&prev| {
+                let prev_cost = best[index(prev_mask, prev)];
+                prev_cost != i32::MAX && prev_cost + edge(prev, last) == cost
+            })
+            .unwrap();
+        order.push(prev);
+        cost = best[index(prev_mask, prev)];
+        mask = prev_mask;
+        last = prev;
+    }
+    order.reverse();
+
+    Route {
+        nodes: order.into_iter().map(|i| nodes[i]).collect(),
+        length: i64::from(sign * length),
+    }
+}
+
+/// Indices of the set bits, lowest first
+fn bits(mut mask: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask.checked_sub(1)?;
+        Some(bit)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Square distance matrices with up to 7 nodes, not necessarily symmetric, and like the
+    /// puzzles without a distance from a node to itself
+    fn distances() -> impl Strategy<Value = Vec<Vec<i64>>> {
+        (1..=7_usize)
+            .prop_flat_map(|n| prop::collection::vec(prop::collection::vec(-50..100_i64, n), n))
+            .prop_map(|mut distances| {
+                for (i, row) in distances.iter_mut().enumerate() {
+                    row[i] = 0;
+                }
+                distances
+            })
+    }
+
+    fn objectives() -> impl Strategy<Value = Objective> {
+        prop_oneof![Just(Objective::Shortest), Just(Objective::Longest)]
+    }
+
+    fn length(distances: &[Vec<i64>], nodes: &[usize]) -> i64 {
+        nodes
+            .iter()
+            .tuple_windows()
+            .map(|(&a, &b)| distances[a][b])
+            .sum()
+    }
+
+    fn best(lengths: impl Iterator<Item = i64>, objective: Objective) -> i64 {
+        match objective {
+            Objective::Shortest => lengths.min().unwrap(),
+            Objective::Longest => lengths.max().unwrap(),
+        }
+    }
+
+    fn assert_visits_all(nodes: &[usize], n: usize) -> Result<(), TestCaseError> {
+        prop_assert_eq!(
+            nodes.iter().copied().sorted().collect_vec(),
+            (0..n).collect_vec()
+        );
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn path_matches_brute_force(distances in distances(), objective in objectives()) {
+            let n = distances.len();
+            let route = path(&distances, objective);
+            assert_visits_all(&route.nodes, n)?;
+            prop_assert_eq!(length(&distances, &route.nodes), route.length);
+
+            let lengths = (0..n).permutations(n).map(|p| length(&distances, &p));
+            prop_assert_eq!(route.length, best(lengths, objective));
+        }
+
+        #[test]
+        fn cycle_matches_brute_force(distances in distances(), objective in objectives()) {
+            let n = distances.len();
+            let route = cycle(&distances, objective);
+            assert_visits_all(&route.nodes, n)?;
+            prop_assert_eq!(route.nodes[0], 0);
+            let closed = |nodes: &[usize]| length(&distances, &[nodes, &[0]].concat());
+            prop_assert_eq!(closed(&route.nodes), route.length);
+
+            let lengths = (1..n)
+                .permutations(n - 1)
+                .map(|p| closed(&[&[0], &p[..]].concat()));
+            prop_assert_eq!(route.length, best(lengths, objective));
+        }
+    }
+}
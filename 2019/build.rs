@@ -0,0 +1,29 @@
+use std::{fmt::Write, path::Path};
+
+const YEAR: u32 = 2019;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if std::env::var_os("CARGO_FEATURE_EMBED_INPUTS").is_none() {
+        return;
+    }
+
+    let mut code = String::new();
+    for (name, directory) in [("INPUTS", "input"), ("EXAMPLES", "examples")] {
+        let directory = Path::new("..").join(directory).join(YEAR.to_string());
+        println!("cargo:rerun-if-changed={}", directory.display());
+
+        writeln!(code, "pub(crate) const {name}: [Option<&str>; 25] = [").unwrap();
+        for day in 1..=25 {
+            match directory.join(format!("{day}.txt")).canonicalize() {
+                Ok(path) => writeln!(code, "    Some(include_str!({:?})),", path.display()),
+                Err(_) => writeln!(code, "    None,"),
+            }
+            .unwrap();
+        }
+        writeln!(code, "];").unwrap();
+    }
+
+    let out_path = Path::new(&std::env::var_os("OUT_DIR").unwrap()).join("embedded.rs");
+    std::fs::write(out_path, code).expect("Should have been able to write the embedded inputs");
+}
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 mod day01;
 mod day02;
@@ -25,6 +25,8 @@ mod day22;
 mod day23;
 mod day24;
 mod day25;
+mod history;
+mod status;
 
 type ProblemFns = (fn(&str) -> String, fn(&str) -> String);
 
@@ -60,6 +62,9 @@ const DAYS: [ProblemFns; 2] = [
 
 #[derive(Parser, Debug)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The day which problem to run
     #[arg(short, long, default_value_t = DAYS.len() as u8, value_parser = clap::value_parser!(u8).range(1..=(DAYS.len() as i64)))]
     day: u8,
@@ -69,12 +74,33 @@ struct Args {
     all: bool,
 
     /// Use the example instead of the full input
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, global = true, default_value_t = false)]
     example: bool,
 
     /// Overwrite environment or default loglevel
     #[arg(short, long)]
     loglevel: Option<String>,
+
+    /// Append the timings of an --all run to the history file
+    #[arg(long, default_value_t = false, requires = "all")]
+    record: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show which days of this year are implemented, have inputs and pass their examples,
+    /// `status.sh` in the repository root shows all years
+    Status,
+    /// Show the recorded runtimes of a day across commits
+    History {
+        /// The day which runtimes to show
+        #[arg(short, long, value_parser = clap::value_parser!(u8).range(1..=(DAYS.len() as i64)))]
+        day: u8,
+
+        /// Relative runtime increase over the previous commit that counts as slower
+        #[arg(short, long, default_value_t = 1.2)]
+        threshold: f64,
+    },
 }
 
 fn main() {
@@ -87,23 +113,54 @@ fn main() {
     }
     pretty_env_logger::init();
 
+    #[cfg(feature = "embed-inputs")]
+    embedded::report_missing(args.example);
+
+    match args.command {
+        Some(Command::Status) => return status::print_status(),
+        Some(Command::History { day, threshold }) => {
+            return history::print_history(day, args.example, threshold)
+        }
+        None => {}
+    }
+
     if args.all {
-        let duration = (1..=DAYS.len())
+        let timings = (1..=DAYS.len())
             .map(|day| run_day(day as u8, args.example))
+            .collect::<Vec<_>>();
+        let duration = timings
+            .iter()
+            .flatten()
+            .flatten()
             .sum::<std::time::Duration>();
         log::info!("Total time for all days: {duration:?}");
+        if args.record {
+            history::append(&timings, args.example);
+        }
     } else {
         run_day(args.day, args.example);
     }
 }
 
-fn run_day(day: u8, example: bool) -> std::time::Duration {
-    let day_idx: usize = day.saturating_sub(1).into();
-    let (part_1, part_2) = DAYS[day_idx];
+fn try_read_input(day: u8, example: bool) -> Option<String> {
+    #[cfg(feature = "embed-inputs")]
+    if let Some(input) = embedded::get(day, example) {
+        return Some(input.to_owned());
+    }
+
     let directory = if example { "examples" } else { "input" };
     let input_path = format!("../{directory}/{YEAR}/{day}.txt");
-    let input =
-        std::fs::read_to_string(input_path).expect("Should have been able to read the file");
+    std::fs::read_to_string(input_path).ok()
+}
+
+/// None if the day was skipped because its input is missing
+fn run_day(day: u8, example: bool) -> Option<[std::time::Duration; 2]> {
+    let day_idx: usize = day.saturating_sub(1).into();
+    let (part_1, part_2) = DAYS[day_idx];
+    let Some(input) = try_read_input(day, example) else {
+        log::warn!("Skipping day {day}, no input found");
+        return None;
+    };
 
     log::info!("Selected day {day}");
     log::info!("Running part 1...");
@@ -119,5 +176,25 @@ fn run_day(day: u8, example: bool) -> std::time::Duration {
     log::info!("Done in {elapsed2:?}, Result: {res}");
     log::info!("Total time: {:?}", elapsed1 + elapsed2);
 
-    elapsed1 + elapsed2
+    Some([elapsed1, elapsed2])
+}
+
+#[cfg(feature = "embed-inputs")]
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/embedded.rs"));
+
+    pub(crate) fn get(day: u8, example: bool) -> Option<&'static str> {
+        let embedded = if example { EXAMPLES } else { INPUTS };
+        embedded[usize::from(day) - 1]
+    }
+
+    pub(crate) fn report_missing(example: bool) {
+        let missing = (1..=super::DAYS.len() as u8)
+            .filter(|&day| get(day, example).is_none())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let kind = if example { "example" } else { "input" };
+            log::warn!("Built without {kind} for days {missing:?}, these are read from disk");
+        }
+    }
 }
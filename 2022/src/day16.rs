@@ -10,6 +10,8 @@ use nom::{
 };
 use petgraph::{algo::dijkstra, prelude::NodeIndex, visit::IntoNodeReferences, Graph, Undirected};
 
+use crate::memo::Memo;
+
 type AocGraph<'a> = Graph<(&'a str, u64), u64, Undirected, u32>;
 
 pub(crate) fn part_1(input: &str) -> String {
@@ -26,7 +28,7 @@ pub(crate) fn part_1(input: &str) -> String {
     }
 
     // best: ["IZ", "CU", "QZ", "TU", "UZ", "FF", "GG", "ZL", "SY"] with 1641
-    let mut cache = HashMap::new();
+    let mut cache = Memo::new("day16 single explorer");
     let score = calculate_score(
         &value_nodes,
         &weights,
@@ -55,13 +57,15 @@ pub(crate) fn part_2(input: &str) -> String {
 
     let possibilities = 2u64.pow(value_nodes.len() as u32 - 1);
     let mut score = 0;
+    let mut cache = Memo::new("day16 partitioned explorers");
 
     // own_best: ["JH", "OI", "GG", "ZL", "XF", "TR", "SZ", "FF"] (not in this order)
     // ele_best: ["QZ", "TU", "IZ", "YL", "UZ", "PA", "CU"] (not in this order)
     for (own_nodes, ele_nodes) in
         (0..possibilities).map(|selection| select_elements(&value_nodes, selection))
     {
-        let mut cache = HashMap::new();
+        // scores depend on the partition, so entries can't be reused across them
+        cache.clear();
         let own_score = calculate_score(
             &own_nodes,
             &weights,
@@ -109,7 +113,7 @@ fn calculate_score(
     position: NodeIndex,
     time_left: u64,
     open_list: Vec<NodeIndex>,
-    cache: &mut HashMap<(NodeIndex, u64, Vec<NodeIndex>), u64>,
+    cache: &mut Memo<(NodeIndex, u64, Vec<NodeIndex>), u64>,
 ) -> u64 {
     let position_paths = paths.get(&position).unwrap();
     possible_nodes
@@ -126,8 +130,7 @@ fn calculate_score(
             open_list.push(node);
             open_list.sort();
             let key = (node, time_left, open_list.clone());
-            let possible_score_remaining = if let Some(&possible_score_remaining) = cache.get(&key)
-            {
+            let possible_score_remaining = if let Some(possible_score_remaining) = cache.get(&key) {
                 possible_score_remaining
             } else {
                 let value = calculate_score(
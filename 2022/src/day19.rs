@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use nom::{
     bytes::complete::tag,
     character::complete::u16 as Num,
@@ -7,6 +5,8 @@ use nom::{
     Finish, IResult,
 };
 
+use crate::memo::Memo;
+
 type Num = u16;
 
 pub(crate) fn part_1(input: &str) -> String {
@@ -51,9 +51,10 @@ impl Blueprint {
             geode: Material { amount: 0, rate: 0 },
         };
 
-        let mut cache = HashMap::new();
+        let mut cache = Memo::new("day19 blueprint evaluation");
         let best = self.evaluate_recursive(state, &state, Choice::Nothing, time_limit, &mut cache);
         log::debug!("Evaluation of {} found best: {:?}", self.id, best);
+        log::trace!("Cache of {}: {}", self.id, cache.stats());
         best
     }
 
@@ -63,7 +64,7 @@ impl Blueprint {
         last_state: &State,
         last_choice: Choice,
         time_limit: Num,
-        cache: &mut HashMap<State, State>,
+        cache: &mut Memo<State, State>,
     ) -> State {
         if state.time == time_limit {
             return state;
@@ -105,7 +106,7 @@ impl Blueprint {
             .map(|choice| {
                 let next_state = choice.apply(next_state, self);
                 if let Some(res) = cache.get(&next_state) {
-                    *res
+                    res
                 } else {
                     let res =
                         self.evaluate_recursive(next_state, &state, choice, time_limit, cache);
@@ -0,0 +1,147 @@
+use std::{
+    io::{BufRead, Write},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+const HISTORY_PATH: &str = "history.jsonl";
+
+/// Timings of one day from a single `--all` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Record {
+    timestamp: u64,
+    commit: String,
+    machine: String,
+    example: bool,
+    day: u8,
+    part_1_ns: u64,
+    part_2_ns: u64,
+}
+
+/// Append the timings of all days to the history file, skipped days are left out
+pub(crate) fn append(timings: &[Option<[Duration; 2]>], example: bool) {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let commit = current_commit();
+    let machine = machine_id();
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HISTORY_PATH)
+        .expect("Should have been able to open the history file");
+    for (day_idx, [part_1, part_2]) in timings
+        .iter()
+        .enumerate()
+        .filter_map(|(day_idx, t)| Some((day_idx, (*t)?)))
+    {
+        let record = Record {
+            timestamp,
+            commit: commit.clone(),
+            machine: machine.clone(),
+            example,
+            day: day_idx as u8 + 1,
+            part_1_ns: part_1.as_nanos() as u64,
+            part_2_ns: part_2.as_nanos() as u64,
+        };
+        serde_json::to_writer(&mut file, &record).unwrap();
+        writeln!(file).unwrap();
+    }
+    log::info!("Recorded timings for commit {commit} in {HISTORY_PATH}");
+}
+
+/// Show the runtime trend of a day on this machine, one line per commit
+pub(crate) fn print_history(day: u8, example: bool, threshold: f64) {
+    let machine = machine_id();
+    let Ok(file) = std::fs::File::open(HISTORY_PATH) else {
+        log::warn!("No history recorded yet, use --all --record");
+        return;
+    };
+
+    // fastest run per commit, commits in the order they were first recorded
+    let mut commits: Vec<(String, u32, [Duration; 2])> = vec![];
+    for record in std::io::BufReader::new(file)
+        .lines()
+        .map(|l| serde_json::from_str::<Record>(&l.unwrap()).unwrap())
+        .filter(|r| r.day == day && r.example == example && r.machine == machine)
+    {
+        let timings = [
+            Duration::from_nanos(record.part_1_ns),
+            Duration::from_nanos(record.part_2_ns),
+        ];
+        match commits.iter_mut().find(|(c, _, _)| *c == record.commit) {
+            Some((_, runs, best)) => {
+                *runs += 1;
+                best[0] = best[0].min(timings[0]);
+                best[1] = best[1].min(timings[1]);
+            }
+            None => commits.push((record.commit, 1, timings)),
+        }
+    }
+
+    if commits.is_empty() {
+        log::warn!("No history recorded for day {day} on this machine");
+        return;
+    }
+
+    let mut table = format!(
+        "{:<16}{:>6}{:>24}{:>24}",
+        "commit", "runs", "part 1", "part 2"
+    );
+    let mut previous: Option<[Duration; 2]> = None;
+    for (commit, runs, timings) in &commits {
+        table += &format!("\n{commit:<16}{runs:>6}");
+        let mut slower = vec![];
+        for part in 0..2 {
+            let change = previous.map(|p| timings[part].as_secs_f64() / p[part].as_secs_f64());
+            let cell = match change {
+                Some(change) => format!("{:?} ({:+.0}%)", timings[part], (change - 1.0) * 100.0),
+                None => format!("{:?}", timings[part]),
+            };
+            table += &format!("{cell:>24}");
+            if change.map_or(false, |change| change > threshold) {
+                slower.push(part + 1);
+            }
+        }
+        if !slower.is_empty() {
+            table += &format!("  <- part {slower:?} slower");
+        }
+        previous = Some(*timings);
+    }
+
+    log::info!("Runtime history of day {day} on machine {machine}:\n{table}");
+}
+
+fn current_commit() -> String {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_owned())
+    };
+
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(commit)
+            if git(&["status", "--porcelain", "--", "."]).map_or(false, |s| !s.is_empty()) =>
+        {
+            commit + "-dirty"
+        }
+        Some(commit) => commit,
+        None => "unknown".to_owned(),
+    }
+}
+
+fn machine_id() -> String {
+    std::fs::read_to_string("/etc/machine-id")
+        .ok()
+        .map(|id| id.trim().chars().take(12).collect())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
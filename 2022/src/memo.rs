@@ -0,0 +1,137 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// Capacity for newly created caches, 0 means unbounded
+static DEFAULT_CAPACITY: AtomicUsize = AtomicUsize::new(0);
+
+/// Statistics of dropped caches, collected until the runner takes them
+static REPORTS: Mutex<Vec<(&'static str, MemoStats)>> = Mutex::new(Vec::new());
+
+/// Bound the size of all caches created from now on
+pub(crate) fn set_default_capacity(capacity: Option<usize>) {
+    DEFAULT_CAPACITY.store(capacity.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Take the statistics of all caches dropped since the last call
+pub(crate) fn take_reports() -> Vec<(&'static str, MemoStats)> {
+    std::mem::take(&mut *REPORTS.lock().unwrap())
+}
+
+/// Cache for recursive searches, optionally bounded in size.
+///
+/// When the capacity set with [`set_default_capacity`] is reached, the oldest
+/// entry is evicted. The hit, miss
+/// and size counters are reported to the runner once the cache is dropped.
+#[derive(Debug)]
+pub(crate) struct Memo<K, V> {
+    name: &'static str,
+    map: HashMap<K, V>,
+    order: VecDeque<K>,
+    capacity: Option<usize>,
+    stats: MemoStats,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct MemoStats {
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) evictions: u64,
+    pub(crate) max_size: usize,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            map: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: match DEFAULT_CAPACITY.load(Ordering::Relaxed) {
+                0 => None,
+                capacity => Some(capacity),
+            },
+            stats: MemoStats::default(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &K) -> Option<V> {
+        let res = self.map.get(key).cloned();
+        if res.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        res
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if let Some(capacity) = self.capacity {
+            if !self.map.contains_key(&key) {
+                while self.map.len() >= capacity {
+                    let oldest = self.order.pop_front().unwrap();
+                    self.map.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+                self.order.push_back(key.clone());
+            }
+        }
+        self.map.insert(key, value);
+        self.stats.max_size = self.stats.max_size.max(self.map.len());
+    }
+
+    /// Remove all entries but keep the counters and the allocation
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+
+    pub(crate) fn stats(&self) -> MemoStats {
+        self.stats
+    }
+}
+
+impl<K, V> Drop for Memo<K, V> {
+    fn drop(&mut self) {
+        if self.stats.hits + self.stats.misses > 0 {
+            REPORTS.lock().unwrap().push((self.name, self.stats));
+        }
+    }
+}
+
+impl MemoStats {
+    pub(crate) fn merge(self, other: Self) -> Self {
+        Self {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            evictions: self.evictions + other.evictions,
+            max_size: self.max_size.max(other.max_size),
+        }
+    }
+
+    pub(crate) fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl Display for MemoStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.1}% hit rate), {} evictions, max size {}",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0,
+            self.evictions,
+            self.max_size
+        )
+    }
+}
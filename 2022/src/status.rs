@@ -0,0 +1,117 @@
+use std::{fmt::Write, panic::AssertUnwindSafe};
+
+use crate::{try_read_input, DAYS, YEAR};
+
+const PLACEHOLDER: &str = "Not implemented!";
+
+type Cell = fn(&DayStatus) -> char;
+
+pub(crate) fn print_status() {
+    // examples that panic are reported in the grid instead
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let days = (1..=25).map(DayStatus::collect).collect::<Vec<_>>();
+    std::panic::set_hook(default_hook);
+
+    let mut grid = format!("{YEAR:<8}");
+    for day in 1..=25 {
+        write!(grid, "{day:>3}").unwrap();
+    }
+    let rows: [(&str, Cell); 5] = [
+        ("part 1", |d| d.parts[0]),
+        ("part 2", |d| d.parts[1]),
+        ("input", |d| d.input),
+        ("example", |d| d.example),
+        ("answers", |d| d.answers),
+    ];
+    for (name, cell) in rows {
+        write!(grid, "\n{name:<8}").unwrap();
+        for day in &days {
+            write!(grid, "{:>3}", cell(day)).unwrap();
+        }
+    }
+
+    log::info!("Status of {YEAR}:\n{grid}");
+    log::info!(
+        "parts:   * implemented, ~ returns the placeholder, - not registered, ? nothing to check with"
+    );
+    log::info!("input:   * present");
+    log::info!(
+        "example: * matches recorded answers, x mismatch, ! panicked, ? no answers recorded"
+    );
+    log::info!("answers: * recorded for both parts, 1 or 2 only for that part");
+}
+
+struct DayStatus {
+    parts: [char; 2],
+    input: char,
+    example: char,
+    answers: char,
+}
+
+impl DayStatus {
+    fn collect(day: u8) -> Self {
+        let example = try_read_input(day, true);
+        let example_answers = read_answers(day, true);
+        let input_answers = read_answers(day, false);
+
+        let mut parts = ['-', '-'];
+        let mut example_status = if example.is_some() { '?' } else { ' ' };
+        if let Some(&(part_1, part_2)) = DAYS.get(usize::from(day) - 1) {
+            for (idx, part) in [part_1, part_2].into_iter().enumerate() {
+                let Some(example) = &example else {
+                    parts[idx] = '?';
+                    continue;
+                };
+                match std::panic::catch_unwind(AssertUnwindSafe(|| (part)(example))) {
+                    Ok(res) if res == PLACEHOLDER => parts[idx] = '~',
+                    Ok(res) => {
+                        parts[idx] = '*';
+                        match &example_answers[idx] {
+                            Some(answer) if answer != res.trim() => example_status = 'x',
+                            Some(_) if example_status == '?' => example_status = '*',
+                            _ => {}
+                        }
+                    }
+                    Err(_) => {
+                        parts[idx] = '*';
+                        example_status = '!';
+                    }
+                }
+            }
+        }
+
+        Self {
+            parts,
+            input: if try_read_input(day, false).is_some() {
+                '*'
+            } else {
+                ' '
+            },
+            example: example_status,
+            answers: match input_answers {
+                [Some(_), Some(_)] => '*',
+                [Some(_), None] => '1',
+                [None, Some(_)] => '2',
+                [None, None] => ' ',
+            },
+        }
+    }
+}
+
+/// Read the recorded answers next to the input, one `part: answer` per line
+fn read_answers(day: u8, example: bool) -> [Option<String>; 2] {
+    let directory = if example { "examples" } else { "input" };
+    let answers_path = format!("../{directory}/{YEAR}/{day}.answers");
+    let mut answers = [None, None];
+    if let Ok(content) = std::fs::read_to_string(answers_path) {
+        for (part, answer) in content.lines().filter_map(|l| l.split_once(": ")) {
+            match part.trim() {
+                "1" => answers[0] = Some(answer.trim().to_owned()),
+                "2" => answers[1] = Some(answer.trim().to_owned()),
+                _ => log::warn!("Unknown part {part} in answers for day {day}"),
+            }
+        }
+    }
+    answers
+}